@@ -0,0 +1,478 @@
+use crate::{
+    error::*,
+    utils::{b64_decode_vec, b64_encode, decrypt, encrypt},
+    Cursor, CursorEnvelope,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use snafu::{ensure, OptionExt};
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// minimum length of the secret of a key
+const MIN_SECRET: usize = 32;
+
+/// Encode and decode the cursors of a `SqlQuery`.
+///
+/// By default cursors are plain base64. With a signing key, cursors are in the form of
+/// `payload.key_id.signature` where the signature is an HMAC-SHA256 over the key id and the
+/// payload, so clients can't forge them. With an encryption key, cursors are in the form of
+/// `key_id.ciphertext`, encrypted with XChaCha20-Poly1305, so clients can neither read nor forge
+/// them. The first key signs or encrypts new cursors, the other keys are only used to decode
+/// cursors issued before a key rotation.
+///
+/// With a TTL, cursors carry the time they were issued and are rejected once they are older
+/// than the TTL. Cursors issued without a timestamp are rejected too, unless they are allowed
+/// while rolling out the TTL. Clients could change the time of unsigned cursors, so a TTL
+/// requires a key (see `validate`)
+#[derive(Clone)]
+pub struct CursorCodec {
+    keys: Vec<CursorKey>,
+    encrypted: bool,
+    ttl: Option<Duration>,
+    allow_untimed: bool,
+    clock: Arc<dyn Clock>,
+}
+
+/// Source of the current time used to check cursor expiry. Closures returning a `SystemTime`
+/// can be used as a clock, e.g. in tests
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[derive(Clone, PartialEq, Eq)]
+struct CursorKey {
+    id: String,
+    secret: Vec<u8>,
+}
+
+impl CursorCodec {
+    /// cursors are not signed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sign cursors with the given key. The secret must have at least 32 bytes and should be
+    /// random
+    pub fn signed(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Result<Self> {
+        Self::new().with_key(id, secret)
+    }
+
+    /// encrypt cursors with the given key. The encryption key is derived from the secret, which
+    /// must have at least 32 bytes and should be random
+    pub fn encrypted(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Result<Self> {
+        Ok(Self {
+            encrypted: true,
+            ..Self::signed(id, secret)?
+        })
+    }
+
+    /// add a key, its secret must have at least 32 bytes. The first key signs or encrypts new
+    /// cursors, the others only decode existing ones
+    pub fn with_key(mut self, id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Result<Self> {
+        let (id, secret) = (id.into(), secret.into());
+        ensure!(
+            secret.len() >= MIN_SECRET,
+            WeakSecretSnafu {
+                id,
+                min: MIN_SECRET
+            }
+        );
+        self.keys.push(CursorKey { id, secret });
+        Ok(self)
+    }
+
+    /// reject cursors issued longer than `ttl` ago. The codec must sign or encrypt cursors
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// accept cursors issued without a timestamp despite the TTL, e.g. cursors issued before the
+    /// TTL was set
+    pub fn allow_untimed_cursors(mut self) -> Self {
+        self.allow_untimed = true;
+        self
+    }
+
+    /// use the clock instead of the system clock to check cursor expiry
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// check the configuration, i.e. that cursors can only expire if they are tamper-proof
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.ttl.is_none() || self.is_signed(), UnsignedTtlSnafu);
+        Ok(())
+    }
+
+    /// whether cursors are signed or encrypted, so they are tamper-proof
+    pub fn is_signed(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted && self.is_signed()
+    }
+
+    /// encode the cursor, signed with the first key if any
+    pub fn encode(&self, cursor: &Cursor) -> String {
+        self.encode_envelope(&CursorEnvelope::new(cursor.clone()))
+    }
+
+    /// decode a cursor generated by `encode`, verifying its signature if the codec has keys
+    pub fn decode(&self, s: &str) -> Result<Cursor> {
+        self.decode_envelope(s).map(|envelope| envelope.cursor)
+    }
+
+    /// encode the cursor envelope, signed or encrypted with the first key if any
+    pub fn encode_envelope(&self, envelope: &CursorEnvelope) -> String {
+        let bytes = match self.ttl {
+            Some(_) if envelope.issued_at.is_none() => CursorEnvelope {
+                issued_at: Some(self.now()),
+                ..envelope.clone()
+            }
+            .to_bytes(),
+            _ => envelope.to_bytes(),
+        };
+        match self.keys.first() {
+            None => b64_encode(bytes),
+            Some(key) if self.encrypted => {
+                let id = b64_encode(&key.id);
+                let data = encrypt(&key.cipher_key(), id.as_bytes(), &bytes);
+                format!("{id}.{}", b64_encode(data))
+            }
+            Some(key) => {
+                let payload = b64_encode(bytes);
+                let id = b64_encode(&key.id);
+                let signature = b64_encode(key.sign(&id, &payload).finalize().into_bytes());
+                format!("{payload}.{id}.{signature}")
+            }
+        }
+    }
+
+    /// decode a cursor envelope, verifying its signature or decrypting it if the codec has keys,
+    /// and that it has not expired
+    pub fn decode_envelope(&self, s: &str) -> Result<CursorEnvelope> {
+        let envelope = self.open_envelope(s)?;
+        self.check_expiry(&envelope, s)?;
+        Ok(envelope)
+    }
+
+    /// verify or decrypt the cursor envelope, without checking its expiry
+    pub(crate) fn open_envelope(&self, s: &str) -> Result<CursorEnvelope> {
+        let bytes = match self.encrypted {
+            _ if !self.is_signed() => b64_decode_vec(s)?,
+            true => self.decrypt(s)?,
+            false => self.verify(s)?,
+        };
+        CursorEnvelope::from_bytes(&bytes).context(InvalidCursorSnafu { cursor: s })
+    }
+
+    /// check that the envelope of the cursor `s` has not expired
+    pub(crate) fn check_expiry(&self, envelope: &CursorEnvelope, s: &str) -> Result<()> {
+        let now = self.now();
+        let ttl_expires_at = envelope
+            .issued_at
+            .zip(self.ttl)
+            .map(|(t, ttl)| t.saturating_add(ttl.as_secs()));
+        let untimed = self.ttl.is_some() && envelope.issued_at.is_none() && !self.allow_untimed;
+        let expired = untimed
+            || [envelope.expires_at, ttl_expires_at]
+                .into_iter()
+                .flatten()
+                .any(|t| now >= t);
+        ensure!(!expired, CursorExpiredSnafu { cursor: s });
+        Ok(())
+    }
+
+    /// current unix timestamp in seconds
+    fn now(&self) -> u64 {
+        self.clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+
+    /// verify the signature and return the encoded envelope
+    fn verify(&self, s: &str) -> Result<Vec<u8>> {
+        let mut parts = s.split('.');
+        let (Some(payload), Some(id), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return InvalidSignatureSnafu { cursor: s }.fail();
+        };
+        let key = self
+            .find_key(id)
+            .context(InvalidSignatureSnafu { cursor: s })?;
+        let signature = b64_decode_vec(signature).unwrap_or_default();
+        ensure!(
+            key.sign(id, payload).verify_slice(&signature).is_ok(),
+            InvalidSignatureSnafu { cursor: s }
+        );
+
+        b64_decode_vec(payload)
+    }
+
+    /// decrypt the cursor and return the encoded envelope
+    fn decrypt(&self, s: &str) -> Result<Vec<u8>> {
+        s.split_once('.')
+            .and_then(|(id, data)| {
+                let key = self.find_key(id)?;
+                let data = b64_decode_vec(data).ok()?;
+                decrypt(&key.cipher_key(), id.as_bytes(), &data)
+            })
+            .context(CursorDecryptSnafu { cursor: s })
+    }
+
+    /// find the key by its base64 encoded id
+    fn find_key(&self, id: &str) -> Option<&CursorKey> {
+        let id = b64_decode_vec(id).ok()?;
+        self.keys.iter().find(|key| key.id.as_bytes() == id)
+    }
+}
+
+impl CursorKey {
+    fn sign(&self, id: &str, payload: &str) -> HmacSha256 {
+        let mut mac = self.mac();
+        mac.update(id.as_bytes());
+        mac.update(b".");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// derive the encryption key from the secret, so the same secret is never used directly
+    /// for both signing and encryption
+    fn cipher_key(&self) -> [u8; 32] {
+        let mut mac = self.mac();
+        mac.update(b"data-pager cursor encryption");
+        mac.finalize().into_bytes().into()
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.secret).expect("HMAC can take key of any size")
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> SystemTime + Send + Sync,
+{
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+impl Default for CursorCodec {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            encrypted: false,
+            ttl: None,
+            allow_untimed: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl PartialEq for CursorCodec {
+    fn eq(&self, other: &Self) -> bool {
+        // the clock is not part of the configuration
+        self.keys == other.keys
+            && self.encrypted == other.encrypted
+            && self.ttl == other.ttl
+            && self.allow_untimed == other.allow_untimed
+    }
+}
+
+impl Eq for CursorCodec {}
+
+impl fmt::Debug for CursorCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print the secrets
+        let ids = self.keys.iter().map(|key| &key.id).collect::<Vec<_>>();
+        f.debug_struct("CursorCodec")
+            .field("keys", &ids)
+            .field("encrypted", &self.encrypted)
+            .field("ttl", &self.ttl)
+            .field("allow_untimed", &self.allow_untimed)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+    const OTHER: &str = "another secret of at least 32 bytes";
+
+    #[test]
+    fn signed_cursor_should_round_trip() -> Result<()> {
+        let codec = CursorCodec::signed("k1", SECRET)?;
+        for cursor in [
+            Cursor::Offset(20),
+            Cursor::After(vec![1.into(), "a".into()]),
+        ] {
+            let s = codec.encode(&cursor);
+            assert_eq!(s.split('.').count(), 3);
+            assert_eq!(codec.decode(&s)?, cursor);
+        }
+
+        // unsigned codec produces the bare cursor
+        let codec = CursorCodec::new();
+        assert_eq!(
+            codec.encode(&Cursor::Offset(20)),
+            Cursor::Offset(20).encode()
+        );
+        assert_eq!(
+            format!("{:?}", CursorCodec::signed("k1", SECRET)?),
+            r#"CursorCodec { keys: ["k1"], encrypted: false, ttl: None, allow_untimed: false }"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn signed_cursor_should_reject_tampering() -> Result<()> {
+        let codec = CursorCodec::signed("k1", SECRET)?;
+        let s = codec.encode(&Cursor::Offset(20));
+        let (_, rest) = s.split_once('.').unwrap();
+
+        let forged = format!("{}.{rest}", Cursor::Offset(1_000_000).encode());
+        let other = CursorCodec::signed("k1", OTHER)?.encode(&Cursor::Offset(20));
+        for s in [forged, other, Cursor::Offset(20).encode(), format!("{s}.x")] {
+            assert!(matches!(
+                codec.decode(&s),
+                Err(Error::InvalidSignature { .. })
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn signed_cursor_should_support_key_rotation() -> Result<()> {
+        let old = CursorCodec::signed("k1", SECRET)?;
+        let new = CursorCodec::signed("k2", OTHER)?.with_key("k1", SECRET)?;
+        let cursor = Cursor::Before(vec![42.into()]);
+
+        assert_eq!(new.decode(&old.encode(&cursor))?, cursor);
+        let s = new.encode(&cursor);
+        assert_eq!(new.decode(&s)?, cursor);
+        // retired keys are rejected
+        assert!(CursorCodec::signed("k2", OTHER)?
+            .decode(&old.encode(&cursor))
+            .is_err());
+        assert!(old.decode(&s).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn expired_cursor_should_be_rejected() -> Result<()> {
+        let codec = CursorCodec::signed("k1", SECRET)?;
+        let mut envelope = CursorEnvelope::new(Cursor::Offset(10));
+        envelope.expires_at = Some(1);
+        let err = codec.decode(&codec.encode_envelope(&envelope)).unwrap_err();
+        assert!(matches!(err, Error::CursorExpired { .. }));
+
+        envelope.expires_at = Some(u64::MAX);
+        assert_eq!(
+            codec.decode_envelope(&codec.encode_envelope(&envelope))?,
+            envelope
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn encrypted_cursor_should_be_opaque() -> Result<()> {
+        let codec = CursorCodec::encrypted("k1", SECRET)?;
+        let cursor = Cursor::After(vec!["tyr@example.com".into(), 42.into()]);
+        let s = codec.encode(&cursor);
+        assert_eq!(codec.decode(&s)?, cursor);
+        // random nonce, sort key values are not visible
+        assert_ne!(codec.encode(&cursor), s);
+        let (_, data) = s.split_once('.').unwrap();
+        let data = b64_decode_vec(data)?;
+        assert!(!data.windows(3).any(|w| w == b"tyr"));
+
+        // rotate the key
+        let rotated = CursorCodec::encrypted("k2", OTHER)?.with_key("k1", SECRET)?;
+        assert_eq!(rotated.decode(&s)?, cursor);
+
+        let mut tampered = s.clone().into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        let signed = CursorCodec::signed("k1", SECRET)?.encode(&cursor);
+        for s in [tampered, signed, cursor.encode()] {
+            assert!(matches!(codec.decode(&s), Err(Error::CursorDecrypt { .. })));
+        }
+
+        assert!(matches!(
+            CursorCodec::encrypted("k3", "short secret"),
+            Err(Error::WeakSecret { min: 32, .. })
+        ));
+        assert!(matches!(
+            CursorCodec::signed("k3", ""),
+            Err(Error::WeakSecret { min: 32, .. })
+        ));
+        assert!(matches!(
+            codec.with_key("k3", "short secret"),
+            Err(Error::WeakSecret { min: 32, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_should_expire_after_ttl() -> Result<()> {
+        let now = Arc::new(AtomicU64::new(1_700_000_000));
+        let clock = {
+            let now = now.clone();
+            move || UNIX_EPOCH + Duration::from_secs(now.load(Ordering::Relaxed))
+        };
+        let codec = CursorCodec::signed("k1", SECRET)?
+            .with_ttl(Duration::from_secs(3600))
+            .with_clock(clock);
+
+        let s = codec.encode(&Cursor::Offset(10));
+        let envelope = codec.decode_envelope(&s)?;
+        assert_eq!(envelope.issued_at, Some(1_700_000_000));
+
+        now.fetch_add(3599, Ordering::Relaxed);
+        assert_eq!(codec.decode(&s)?, Cursor::Offset(10));
+        now.fetch_add(1, Ordering::Relaxed);
+        assert!(matches!(codec.decode(&s), Err(Error::CursorExpired { .. })));
+
+        // cursors without issued-at can't be checked against the TTL, unless allowed
+        let legacy = CursorCodec::signed("k1", SECRET)?.encode(&Cursor::Offset(10));
+        assert!(matches!(
+            codec.decode(&legacy),
+            Err(Error::CursorExpired { .. })
+        ));
+        let codec = codec.allow_untimed_cursors();
+        assert_eq!(codec.decode(&legacy)?, Cursor::Offset(10));
+        assert!(matches!(codec.decode(&s), Err(Error::CursorExpired { .. })));
+
+        // clients could set the time of unsigned cursors
+        codec.validate()?;
+        let unsigned = CursorCodec::new().with_ttl(Duration::from_secs(3600));
+        assert!(matches!(unsigned.validate(), Err(Error::UnsignedTtl)));
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{error::*, Cursor, PageMode, Pager, SortKey, SqlQuery, Value};
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt};
+
+/// Arguments of a Relay connection field (see the GraphQL Cursor Connections spec). `first` and
+/// `after` page forward, `last` and `before` page backward. The cursors are the ones of the edges
+/// of a connection of the same query
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionArgs {
+    /// number of edges after `after`, or from the start
+    pub first: Option<u64>,
+    pub after: Option<String>,
+    /// number of edges before `before`, or up to the end
+    pub last: Option<u64>,
+    pub before: Option<String>,
+}
+
+/// A page of a Relay connection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    pub edges: Vec<Edge<T>>,
+    pub page_info: ConnectionPageInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge<T> {
+    /// opaque cursor of the node, to be passed back as `after` or `before`
+    pub cursor: String,
+    pub node: T,
+}
+
+/// The `PageInfo` object of a Relay connection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionPageInfo {
+    pub has_previous_page: bool,
+    pub has_next_page: bool,
+    /// cursor of the first edge
+    pub start_cursor: Option<String>,
+    /// cursor of the last edge
+    pub end_cursor: Option<String>,
+}
+
+impl ConnectionArgs {
+    pub fn forward(first: u64, after: Option<String>) -> Self {
+        Self {
+            first: Some(first),
+            after,
+            ..Default::default()
+        }
+    }
+
+    pub fn backward(last: u64, before: Option<String>) -> Self {
+        Self {
+            last: Some(last),
+            before,
+            ..Default::default()
+        }
+    }
+
+    /// make sure the arguments page in one direction only, and ask for at least one edge
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &'static str| InvalidConnectionArgsSnafu { reason };
+        ensure!(
+            self.first != Some(0) && self.last != Some(0),
+            invalid("first and last must be greater than 0")
+        );
+        ensure!(
+            self.first.is_none() || self.last.is_none(),
+            invalid("first and last cannot be used together")
+        );
+        ensure!(
+            self.after.is_none() || self.before.is_none(),
+            invalid("after and before cannot be used together")
+        );
+        ensure!(
+            self.first.is_none() || self.before.is_none(),
+            invalid("first cannot be used with before")
+        );
+        ensure!(
+            self.last.is_none() || self.after.is_none(),
+            invalid("last cannot be used with after")
+        );
+        Ok(())
+    }
+
+    fn is_backward(&self) -> bool {
+        self.last.is_some() || self.before.is_some()
+    }
+}
+
+impl<'a> SqlQuery<'a> {
+    /// the query for a page of a Relay connection. The page size is `first` or `last` (the
+    /// default size if neither is given, 0 is rejected), limited by the page policy. In offset mode `last`
+    /// needs `before`, as the end is unknown without a total
+    pub fn with_connection_args(&self, args: &ConnectionArgs) -> Result<Self> {
+        args.validate()?;
+        let backward = args.is_backward();
+        let page_size = self
+            .page_policy
+            .normalize(args.first.or(args.last).unwrap_or(0));
+
+        let edge_cursor = args.after.as_deref().or(args.before.as_deref());
+        let edge = edge_cursor.map(|s| self.decode_cursor(s)).transpose()?;
+        let invalid = || InvalidCursorSnafu {
+            cursor: edge_cursor.unwrap_or_default(),
+        };
+
+        // the rows before `before` may be fewer than a page in offset mode
+        let mut trimmed_size = None;
+        let cursor = match self.mode {
+            PageMode::Keyset => {
+                let values = match &edge {
+                    Some(cursor) => cursor.keyset().with_context(invalid)?.to_vec(),
+                    None => Vec::new(),
+                };
+                match backward {
+                    true => Some(Cursor::Before(values)),
+                    false => edge.map(|_| Cursor::After(values)),
+                }
+            }
+            PageMode::Offset => {
+                let position = match &edge {
+                    Some(cursor) => Some(cursor.offset().with_context(invalid)?),
+                    None => None,
+                };
+                let start = match (backward, position) {
+                    (false, None) => 0,
+                    (false, Some(position)) => {
+                        position.checked_add(1).context(OffsetOverflowSnafu {
+                            offset: position,
+                            page_size,
+                        })?
+                    }
+                    (true, Some(position)) => {
+                        let start = position.saturating_sub(page_size);
+                        trimmed_size = Some(position - start);
+                        start
+                    }
+                    (true, None) => {
+                        return InvalidConnectionArgsSnafu {
+                            reason: "last requires before when paging by offset",
+                        }
+                        .fail()
+                    }
+                };
+                Some(match edge {
+                    Some(Cursor::Snapshot { id, .. }) => Cursor::Snapshot { id, offset: start },
+                    _ => self.offset_cursor(start),
+                })
+            }
+        };
+
+        let mut query = match &cursor {
+            Some(cursor) => self.with_cursor(cursor),
+            None => Self {
+                cursor: None,
+                ..self.clone()
+            },
+        };
+        query.page_size = page_size;
+        query.validate()?;
+        if let Some(size) = trimmed_size {
+            query.page_size = size;
+        }
+        Ok(query)
+    }
+
+    /// build the connection from the fetched rows (page_size + 1 rows, see `get_pager`), offset
+    /// mode only. The cursor of each edge is derived from its position
+    pub fn connection<T>(&self, mut rows: Vec<T>) -> Result<Connection<T>> {
+        let pager = self.get_pager(&mut rows)?;
+        Ok(self.build_connection(rows, pager, |_| Vec::new()))
+    }
+
+    /// like `connection`, but supports keyset mode too, where the cursor of each edge is derived
+    /// from the sort key of its row
+    pub fn keyset_connection<T: SortKey>(&self, mut rows: Vec<T>) -> Result<Connection<T>> {
+        let pager = self.get_keyset_pager(&mut rows)?;
+        Ok(self.build_connection(rows, pager, |node| self.sort_key(node)))
+    }
+
+    fn build_connection<T>(
+        &self,
+        rows: Vec<T>,
+        pager: Pager<Cursor>,
+        sort_key: impl Fn(&T) -> Vec<Value>,
+    ) -> Connection<T> {
+        let start = self.offset().unwrap_or(0);
+        let snapshot = match self.offset_cursor(start) {
+            Cursor::Snapshot { id, .. } => Some(id),
+            _ => None,
+        };
+
+        let edges: Vec<_> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let cursor = match (self.mode, &snapshot) {
+                    (PageMode::Keyset, _) => Cursor::After(sort_key(&node)),
+                    (PageMode::Offset, Some(id)) => Cursor::Snapshot {
+                        id: id.clone(),
+                        offset: start.saturating_add(i as u64),
+                    },
+                    (PageMode::Offset, None) => Cursor::Offset(start.saturating_add(i as u64)),
+                };
+                Edge {
+                    cursor: self.encode_cursor(&cursor),
+                    node,
+                }
+            })
+            .collect();
+
+        let page_info = ConnectionPageInfo {
+            has_previous_page: pager.prev.is_some(),
+            has_next_page: pager.next.is_some(),
+            start_cursor: edges.first().map(|edge| edge.cursor.clone()),
+            end_cursor: edges.last().map(|edge| edge.cursor.clone()),
+        };
+        Connection { edges, page_info }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pager::pager_test_utils::generate_test_ids, OrderBy, SqlQueryBuilder};
+    use anyhow::Result;
+
+    fn ids<T: SortKey>(connection: &Connection<T>) -> Vec<Value> {
+        connection
+            .edges
+            .iter()
+            .map(|edge| edge.node.sort_key("id"))
+            .collect()
+    }
+
+    #[test]
+    fn connection_should_page_by_keyset() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![OrderBy::asc("id").not_null()])
+            .mode(PageMode::Keyset)
+            .build()?;
+
+        let page = query.with_connection_args(&ConnectionArgs::forward(3, None))?;
+        assert_eq!(page.to_sql(), "SELECT * FROM users ORDER BY id LIMIT 4");
+        let connection = page.keyset_connection(generate_test_ids(1, 4).into())?;
+        assert_eq!(ids(&connection), vec![1.into(), 2.into(), 3.into()]);
+        assert!(connection.page_info.has_next_page);
+        assert!(!connection.page_info.has_previous_page);
+        assert_eq!(
+            page.decode_cursor(&connection.edges[1].cursor)?,
+            Cursor::After(vec![2.into()])
+        );
+
+        // after the second edge
+        let after = connection.edges[1].cursor.clone();
+        let page = query.with_connection_args(&ConnectionArgs::forward(3, Some(after)))?;
+        assert_eq!(
+            page.to_sql(),
+            "SELECT * FROM users WHERE id > 2 ORDER BY id LIMIT 4"
+        );
+
+        // before the cursor of an edge, rows come back in reversed order
+        let before = connection.page_info.end_cursor.clone();
+        let page = query.with_connection_args(&ConnectionArgs::backward(2, before))?;
+        assert_eq!(
+            page.to_sql(),
+            "SELECT * FROM users WHERE id < 3 ORDER BY id DESC LIMIT 3"
+        );
+        let connection =
+            page.keyset_connection(generate_test_ids(1, 2).into_iter().rev().collect())?;
+        assert_eq!(ids(&connection), vec![1.into(), 2.into()]);
+        assert!(!connection.page_info.has_previous_page);
+        assert!(connection.page_info.has_next_page);
+
+        // the last edges
+        let page = query.with_connection_args(&ConnectionArgs::backward(2, None))?;
+        assert_eq!(
+            page.to_sql(),
+            "SELECT * FROM users ORDER BY id DESC LIMIT 3"
+        );
+        let connection =
+            page.keyset_connection(generate_test_ids(8, 10).into_iter().rev().collect())?;
+        assert_eq!(ids(&connection), vec![9.into(), 10.into()]);
+        assert!(connection.page_info.has_previous_page);
+        assert!(!connection.page_info.has_next_page);
+        Ok(())
+    }
+
+    #[test]
+    fn connection_should_page_by_offset() -> Result<()> {
+        let query = SqlQueryBuilder::default().source("users").build()?;
+
+        let page = query.with_connection_args(&ConnectionArgs::forward(3, None))?;
+        let connection = page.connection(generate_test_ids(1, 4).into())?;
+        let end = connection.page_info.end_cursor.unwrap();
+        assert_eq!(page.decode_cursor(&end)?, Cursor::Offset(2));
+
+        let page = query.with_connection_args(&ConnectionArgs::forward(3, Some(end)))?;
+        assert_eq!(page.to_sql(), "SELECT * FROM users LIMIT 4 OFFSET 3");
+        let connection = page.connection(generate_test_ids(4, 6).into())?;
+        assert!(connection.page_info.has_previous_page);
+        assert!(!connection.page_info.has_next_page);
+
+        // only 3 rows before the first edge of the second page, fewer than asked for
+        let before = connection.edges[0].cursor.clone();
+        let page = query.with_connection_args(&ConnectionArgs::backward(5, Some(before)))?;
+        assert_eq!(page.to_sql(), "SELECT * FROM users LIMIT 4 OFFSET 0");
+        let connection = page.connection(generate_test_ids(1, 4).into())?;
+        assert_eq!(ids(&connection), vec![1.into(), 2.into(), 3.into()]);
+        assert!(connection.page_info.has_next_page);
+        Ok(())
+    }
+
+    #[test]
+    fn connection_args_should_be_validated() -> Result<()> {
+        let query = SqlQueryBuilder::default().source("users").build()?;
+        let err = |args: ConnectionArgs| query.with_connection_args(&args).unwrap_err().to_string();
+
+        assert_eq!(
+            err(ConnectionArgs {
+                first: Some(1),
+                last: Some(1),
+                ..Default::default()
+            }),
+            "Invalid connection arguments: first and last cannot be used together"
+        );
+        assert_eq!(
+            err(ConnectionArgs {
+                first: Some(1),
+                before: Some(Cursor::Offset(1).encode()),
+                ..Default::default()
+            }),
+            "Invalid connection arguments: first cannot be used with before"
+        );
+        assert_eq!(
+            err(ConnectionArgs::forward(0, None)),
+            "Invalid connection arguments: first and last must be greater than 0"
+        );
+        assert!(query
+            .with_connection_args(&ConnectionArgs::backward(
+                0,
+                Some(Cursor::Offset(5).encode())
+            ))
+            .is_err());
+        assert_eq!(
+            err(ConnectionArgs::backward(3, None)),
+            "Invalid connection arguments: last requires before when paging by offset"
+        );
+        let keyset = Cursor::After(vec![1.into()]).encode();
+        assert!(query
+            .with_connection_args(&ConnectionArgs::forward(3, Some(keyset)))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    error::*,
+    utils::{
+        b64_decode_vec, b64_encode, decode_str, decode_values, decode_varint, encode_str,
+        encode_values, encode_varint,
+    },
+    Value,
+};
+use snafu::OptionExt;
+
+/// current version of the cursor envelope
+const VERSION: u8 = 2;
+/// set on the first byte of versioned envelopes. Cursors of version 0 (a plain decimal offset)
+/// start with an ASCII digit
+const VERSIONED: u8 = 0x80;
+
+const KIND_OFFSET: u8 = 0;
+const KIND_AFTER: u8 = 1;
+const KIND_BEFORE: u8 = 2;
+const KIND_SNAPSHOT: u8 = 3;
+
+const FLAG_FINGERPRINT: u8 = 0x01;
+const FLAG_EXPIRES_AT: u8 = 0x02;
+const FLAG_ISSUED_AT: u8 = 0x04;
+
+/// A decoded page cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    /// number of items to skip
+    Offset(u64),
+    /// sort key values of the last row of the previous page. Without values it is the first page
+    After(Vec<Value>),
+    /// sort key values of the first row of the next page, for paging backwards. Without values it
+    /// is the last page
+    Before(Vec<Value>),
+    /// number of items to skip within a snapshot of the result set (e.g. a transaction snapshot
+    /// or a materialized result). The application resolves the snapshot from its id
+    Snapshot { id: String, offset: u64 },
+}
+
+/// The versioned envelope a cursor is encoded in.
+///
+/// It is encoded as a version byte, the kind of the cursor, a flags byte telling which optional
+/// fields are present, the optional fields, then the payload of the cursor. Plain offsets of
+/// version 0 can still be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorEnvelope {
+    pub cursor: Cursor,
+    /// fingerprint of the query the cursor was issued for
+    pub fingerprint: Option<u64>,
+    /// unix timestamp (in seconds) after which the cursor is rejected
+    pub expires_at: Option<u64>,
+    /// unix timestamp (in seconds) when the cursor was issued, checked against the TTL of the
+    /// `CursorCodec`
+    pub issued_at: Option<u64>,
+}
+
+impl Cursor {
+    /// encode the cursor into an opaque base64 string
+    pub fn encode(&self) -> String {
+        CursorEnvelope::new(self.clone()).encode()
+    }
+
+    /// decode a cursor previously generated by `encode`
+    pub fn decode(s: &str) -> Result<Self> {
+        CursorEnvelope::decode(s).map(|envelope| envelope.cursor)
+    }
+
+    /// the sort key values if this is a keyset cursor
+    pub fn keyset(&self) -> Option<&[Value]> {
+        match self {
+            Cursor::After(values) | Cursor::Before(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// the number of items to skip if this is an offset or a snapshot cursor
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Cursor::Offset(offset) | Cursor::Snapshot { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+impl CursorEnvelope {
+    pub fn new(cursor: Cursor) -> Self {
+        Self {
+            cursor,
+            fingerprint: None,
+            expires_at: None,
+            issued_at: None,
+        }
+    }
+
+    /// encode the envelope into an opaque base64 string
+    pub fn encode(&self) -> String {
+        b64_encode(self.to_bytes())
+    }
+
+    /// decode an envelope generated by `encode`, or a cursor of an earlier version
+    pub fn decode(s: &str) -> Result<Self> {
+        Self::from_bytes(&b64_decode_vec(s)?).context(InvalidCursorSnafu { cursor: s })
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let kind = match self.cursor {
+            Cursor::Offset(_) => KIND_OFFSET,
+            Cursor::After(_) => KIND_AFTER,
+            Cursor::Before(_) => KIND_BEFORE,
+            Cursor::Snapshot { .. } => KIND_SNAPSHOT,
+        };
+        let flags = self.fingerprint.map_or(0, |_| FLAG_FINGERPRINT)
+            | self.expires_at.map_or(0, |_| FLAG_EXPIRES_AT)
+            | self.issued_at.map_or(0, |_| FLAG_ISSUED_AT);
+
+        let mut buf = vec![VERSIONED | VERSION, kind, flags];
+        if let Some(fingerprint) = self.fingerprint {
+            buf.extend(fingerprint.to_be_bytes());
+        }
+        if let Some(expires_at) = self.expires_at {
+            encode_varint(expires_at, &mut buf);
+        }
+        if let Some(issued_at) = self.issued_at {
+            encode_varint(issued_at, &mut buf);
+        }
+        match &self.cursor {
+            Cursor::Offset(offset) => encode_varint(*offset, &mut buf),
+            Cursor::After(values) | Cursor::Before(values) => encode_values(values, &mut buf),
+            Cursor::Snapshot { id, offset } => {
+                encode_str(id, &mut buf);
+                encode_varint(*offset, &mut buf);
+            }
+        }
+        buf
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&first, rest) = bytes.split_first()?;
+        match first {
+            b'0'..=b'9' => {
+                let offset = std::str::from_utf8(bytes).ok()?.parse().ok()?;
+                Some(Self::new(Cursor::Offset(offset)))
+            }
+            _ if first == VERSIONED | VERSION => decode_v2(rest),
+            _ => None,
+        }
+    }
+}
+
+fn decode_v2(mut buf: &[u8]) -> Option<CursorEnvelope> {
+    let [kind, flags] = *take_chunk::<2>(&mut buf)?;
+    if flags & !(FLAG_FINGERPRINT | FLAG_EXPIRES_AT | FLAG_ISSUED_AT) != 0 {
+        return None;
+    }
+
+    let fingerprint = match flags & FLAG_FINGERPRINT {
+        0 => None,
+        _ => Some(u64::from_be_bytes(*take_chunk(&mut buf)?)),
+    };
+    let expires_at = match flags & FLAG_EXPIRES_AT {
+        0 => None,
+        _ => Some(decode_varint(&mut buf)?),
+    };
+    let issued_at = match flags & FLAG_ISSUED_AT {
+        0 => None,
+        _ => Some(decode_varint(&mut buf)?),
+    };
+    let cursor = match kind {
+        KIND_OFFSET => Cursor::Offset(decode_varint(&mut buf)?),
+        KIND_AFTER => Cursor::After(decode_values(&mut buf)?),
+        KIND_BEFORE => Cursor::Before(decode_values(&mut buf)?),
+        KIND_SNAPSHOT => Cursor::Snapshot {
+            id: decode_str(&mut buf)?,
+            offset: decode_varint(&mut buf)?,
+        },
+        _ => return None,
+    };
+
+    buf.is_empty().then_some(CursorEnvelope {
+        cursor,
+        fingerprint,
+        expires_at,
+        issued_at,
+    })
+}
+
+fn take_chunk<'a, const N: usize>(buf: &mut &'a [u8]) -> Option<&'a [u8; N]> {
+    let (chunk, rest) = buf.split_first_chunk::<N>()?;
+    *buf = rest;
+    Some(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::encode_u64;
+
+    #[test]
+    fn cursor_should_encode_and_decode() -> Result<()> {
+        let cursors = [
+            Cursor::Offset(10),
+            Cursor::After(vec![
+                Value::Null,
+                true.into(),
+                (-42).into(),
+                1.5.into(),
+                "tyr".into(),
+            ]),
+            Cursor::Before(vec![1.into()]),
+            Cursor::Snapshot {
+                id: "tx-42".into(),
+                offset: 20,
+            },
+        ];
+        for cursor in cursors {
+            assert_eq!(Cursor::decode(&cursor.encode())?, cursor);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_should_reject_malformed_input() {
+        let v2 = VERSIONED | VERSION;
+        assert!(Cursor::decode(&b64_encode([v2, KIND_AFTER, 0, 1, 9])).is_err());
+        assert!(Cursor::decode(&b64_encode([v2, KIND_OFFSET, 0, 1, 0])).is_err());
+        assert!(Cursor::decode(&b64_encode([v2, KIND_OFFSET, 0x80, 1])).is_err());
+        assert!(Cursor::decode(&b64_encode([v2, 9, 0, 1])).is_err());
+        assert!(Cursor::decode(&b64_encode([VERSIONED | 3, KIND_OFFSET, 0, 1])).is_err());
+        assert!(Cursor::decode(&b64_encode([KIND_AFTER, 1, 2, 0, 0, 0, 0, 0, 0, 0, 7])).is_err());
+        assert!(Cursor::decode("").is_err());
+    }
+
+    #[test]
+    fn envelope_should_carry_optional_fields() -> Result<()> {
+        let envelope = CursorEnvelope {
+            cursor: Cursor::Before(vec!["a".into()]),
+            fingerprint: Some(42),
+            expires_at: Some(1_700_000_000),
+            issued_at: Some(1_600_000_000),
+        };
+        assert_eq!(CursorEnvelope::decode(&envelope.encode())?, envelope);
+        assert_eq!(
+            b64_decode_vec(&Cursor::Offset(10).encode())?,
+            [VERSIONED | VERSION, KIND_OFFSET, 0, 10]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn envelope_should_decode_plain_offsets() -> Result<()> {
+        // version 0: plain decimal offset
+        assert_eq!(Cursor::decode(&encode_u64(10))?, Cursor::Offset(10));
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+use crate::{Placeholder, Statement, Value};
+
+/// How a dialect limits the number of rows returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitStyle {
+    /// `LIMIT n OFFSET m`
+    LimitOffset,
+    /// `OFFSET m ROWS FETCH NEXT n ROWS ONLY`, or `FETCH FIRST n ROWS ONLY` without offset
+    OffsetFetch,
+    /// `SELECT TOP (n)` without offset, `OFFSET m ROWS FETCH NEXT n ROWS ONLY` otherwise.
+    /// OFFSET requires an ORDER BY clause in this style
+    Top,
+    /// the query is wrapped to filter on ROWNUM, `SELECT * FROM (query) WHERE ROWNUM <= n`.
+    /// With an offset the row number is selected as an extra `rn` column of the rows
+    RowNum,
+}
+
+/// SQL dialect used to render a `SqlQuery`
+pub trait Dialect {
+    /// placeholder style for bind parameters
+    fn placeholder(&self) -> Placeholder;
+
+    /// how to limit the number of rows
+    fn limit_style(&self) -> LimitStyle {
+        LimitStyle::LimitOffset
+    }
+
+    /// quote a single identifier (no schema or table qualification)
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    }
+
+    /// boolean literal
+    fn bool_literal(&self, value: bool) -> &'static str {
+        if value {
+            "TRUE"
+        } else {
+            "FALSE"
+        }
+    }
+
+    /// render the value as an inline literal
+    fn literal(&self, value: &Value) -> String {
+        match value {
+            Value::Bool(v) => self.bool_literal(*v).to_owned(),
+            v => v.to_sql_literal().into_owned(),
+        }
+    }
+
+    /// whether NULLs sort as the largest values when NULLS FIRST/LAST is not specified
+    fn nulls_largest(&self) -> bool;
+
+    /// whether ORDER BY supports NULLS FIRST/LAST. If not it is emulated with an extra sort key
+    fn supports_nulls_ordering(&self) -> bool {
+        true
+    }
+
+    /// whether row values can be compared, as in `(a, b) > (1, 2)`. If not keyset seeks are
+    /// expanded into OR'ed conditions
+    fn supports_row_values(&self) -> bool {
+        true
+    }
+
+    /// query estimating the number of rows of a table from planner statistics, if supported.
+    /// It returns a single integer
+    fn estimate_count(&self, _schema: Option<&str>, _table: &str) -> Option<Statement> {
+        None
+    }
+}
+
+/// The dialect used by `SqlQuery::to_sql`: LIMIT/OFFSET, identifiers are not quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericDialect(pub Placeholder);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PostgresDialect;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MySqlDialect;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SqliteDialect;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsSqlDialect;
+
+/// Oracle 12c or later (see `Oracle11Dialect` for earlier versions)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OracleDialect;
+
+/// Oracle before 12c, without OFFSET/FETCH. Rows are limited with ROWNUM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Oracle11Dialect;
+
+impl Default for GenericDialect {
+    fn default() -> Self {
+        Self(Placeholder::Question)
+    }
+}
+
+impl Dialect for GenericDialect {
+    fn placeholder(&self) -> Placeholder {
+        self.0
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        ident.to_owned()
+    }
+
+    fn literal(&self, value: &Value) -> String {
+        match value {
+            // the database is unknown, escape backslashes too as MySQL treats them as escapes
+            Value::String(v) => format!("'{}'", v.replace('\\', "\\\\").replace('\'', "''")),
+            v => v.to_sql_literal().into_owned(),
+        }
+    }
+
+    fn nulls_largest(&self) -> bool {
+        true
+    }
+}
+
+impl Dialect for PostgresDialect {
+    fn placeholder(&self) -> Placeholder {
+        Placeholder::Dollar
+    }
+
+    fn nulls_largest(&self) -> bool {
+        true
+    }
+
+    fn estimate_count(&self, schema: Option<&str>, table: &str) -> Option<Statement> {
+        // reltuples is -1 if the table has never been analyzed
+        let name = match schema {
+            Some(schema) => format!(
+                "{}.{}",
+                self.quote_identifier(schema),
+                self.quote_identifier(table)
+            ),
+            None => self.quote_identifier(table),
+        };
+        Some(Statement {
+            sql: "SELECT GREATEST(reltuples, 0)::bigint FROM pg_class WHERE oid = to_regclass($1)"
+                .to_owned(),
+            params: vec![name.into()],
+        })
+    }
+}
+
+impl Dialect for MySqlDialect {
+    fn placeholder(&self) -> Placeholder {
+        Placeholder::Question
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("`{}`", ident.replace('`', "``"))
+    }
+
+    fn literal(&self, value: &Value) -> String {
+        match value {
+            // backslash is an escape character in MySQL string literals
+            Value::String(v) => format!("'{}'", v.replace('\\', "\\\\").replace('\'', "''")),
+            v => v.to_sql_literal().into_owned(),
+        }
+    }
+
+    fn nulls_largest(&self) -> bool {
+        false
+    }
+
+    fn supports_nulls_ordering(&self) -> bool {
+        false
+    }
+
+    fn estimate_count(&self, schema: Option<&str>, table: &str) -> Option<Statement> {
+        Some(Statement {
+            sql: "SELECT TABLE_ROWS FROM information_schema.TABLES WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?"
+                .to_owned(),
+            params: vec![schema.into(), table.into()],
+        })
+    }
+}
+
+impl Dialect for SqliteDialect {
+    fn placeholder(&self) -> Placeholder {
+        Placeholder::Question
+    }
+
+    fn bool_literal(&self, value: bool) -> &'static str {
+        if value {
+            "1"
+        } else {
+            "0"
+        }
+    }
+
+    fn nulls_largest(&self) -> bool {
+        false
+    }
+}
+
+impl Dialect for MsSqlDialect {
+    fn placeholder(&self) -> Placeholder {
+        Placeholder::At
+    }
+
+    fn limit_style(&self) -> LimitStyle {
+        LimitStyle::Top
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("[{}]", ident.replace(']', "]]"))
+    }
+
+    fn bool_literal(&self, value: bool) -> &'static str {
+        if value {
+            "1"
+        } else {
+            "0"
+        }
+    }
+
+    fn nulls_largest(&self) -> bool {
+        false
+    }
+
+    fn supports_nulls_ordering(&self) -> bool {
+        false
+    }
+
+    fn supports_row_values(&self) -> bool {
+        false
+    }
+}
+
+impl Dialect for OracleDialect {
+    fn placeholder(&self) -> Placeholder {
+        Placeholder::Colon
+    }
+
+    fn limit_style(&self) -> LimitStyle {
+        LimitStyle::OffsetFetch
+    }
+
+    fn bool_literal(&self, value: bool) -> &'static str {
+        if value {
+            "1"
+        } else {
+            "0"
+        }
+    }
+
+    fn nulls_largest(&self) -> bool {
+        true
+    }
+
+    fn supports_row_values(&self) -> bool {
+        false
+    }
+}
+
+impl Dialect for Oracle11Dialect {
+    fn placeholder(&self) -> Placeholder {
+        OracleDialect.placeholder()
+    }
+
+    fn limit_style(&self) -> LimitStyle {
+        LimitStyle::RowNum
+    }
+
+    fn bool_literal(&self, value: bool) -> &'static str {
+        OracleDialect.bool_literal(value)
+    }
+
+    fn nulls_largest(&self) -> bool {
+        OracleDialect.nulls_largest()
+    }
+
+    fn supports_row_values(&self) -> bool {
+        OracleDialect.supports_row_values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cursor, Filter, OrderBy, PageMode, SqlQuery, SqlQueryBuilder, TotalPolicy};
+    use anyhow::Result;
+
+    fn offset_query() -> Result<SqlQuery<'static>> {
+        Ok(SqlQueryBuilder::default()
+            .source("users")
+            .projection(vec!["id".into(), "name".into()])
+            .filter(Filter::eq("active", true))
+            .order(vec![OrderBy::asc("name").nulls_last()])
+            .cursor(Cursor::Offset(20).encode())
+            .build()?)
+    }
+
+    fn keyset_query() -> Result<SqlQuery<'static>> {
+        Ok(SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![OrderBy::asc("id").not_null()])
+            .mode(PageMode::Keyset)
+            .cursor(Cursor::After(vec![10.into()]).encode())
+            .build()?)
+    }
+
+    #[test]
+    fn postgres_dialect_should_render() -> Result<()> {
+        let d = PostgresDialect;
+        assert_eq!(
+            offset_query()?.to_statement(&d).sql,
+            r#"SELECT "id", "name" FROM "users" WHERE "active" = $1 ORDER BY "name" NULLS LAST LIMIT $2 OFFSET $3"#
+        );
+        assert_eq!(
+            keyset_query()?.to_statement(&d).sql,
+            r#"SELECT * FROM "users" WHERE "id" > $1 ORDER BY "id" LIMIT $2"#
+        );
+        assert_eq!(d.quote_identifier("my\"table"), "\"my\"\"table\"");
+        Ok(())
+    }
+
+    #[test]
+    fn mysql_dialect_should_render() -> Result<()> {
+        let d = MySqlDialect;
+        assert_eq!(
+            offset_query()?.to_statement(&d).sql,
+            "SELECT `id`, `name` FROM `users` WHERE `active` = ? ORDER BY CASE WHEN `name` IS NULL THEN 1 ELSE 0 END, `name` LIMIT ? OFFSET ?"
+        );
+        assert_eq!(
+            keyset_query()?.to_statement(&d).sql,
+            "SELECT * FROM `users` WHERE `id` > ? ORDER BY `id` LIMIT ?"
+        );
+        assert_eq!(d.quote_identifier("users"), "`users`");
+
+        // native NULL ordering matches, no emulation needed
+        let query = SqlQuery {
+            order: vec![OrderBy::desc("name").nulls_last()],
+            ..offset_query()?
+        };
+        assert_eq!(
+            query.to_statement(&d).sql,
+            "SELECT `id`, `name` FROM `users` WHERE `active` = ? ORDER BY `name` DESC LIMIT ? OFFSET ?"
+        );
+        assert_eq!(d.literal(&"a\\'b".into()), "'a\\\\''b'");
+        Ok(())
+    }
+
+    #[test]
+    fn sqlite_dialect_should_render() -> Result<()> {
+        let d = SqliteDialect;
+        assert_eq!(
+            offset_query()?.to_statement(&d).sql,
+            r#"SELECT "id", "name" FROM "users" WHERE "active" = ? ORDER BY "name" NULLS LAST LIMIT ? OFFSET ?"#
+        );
+        assert_eq!(d.literal(&true.into()), "1");
+        Ok(())
+    }
+
+    #[test]
+    fn mssql_dialect_should_render() -> Result<()> {
+        let d = MsSqlDialect;
+        let stmt = offset_query()?.to_statement(&d);
+        assert_eq!(
+            stmt.sql,
+            "SELECT [id], [name] FROM [users] WHERE [active] = @p1 ORDER BY CASE WHEN [name] IS NULL THEN 1 ELSE 0 END, [name] OFFSET @p2 ROWS FETCH NEXT @p3 ROWS ONLY"
+        );
+        assert_eq!(
+            stmt.params,
+            vec![Value::Bool(true), Value::Int(20), Value::Int(11)]
+        );
+
+        let stmt = keyset_query()?.to_statement(&d);
+        assert_eq!(
+            stmt.sql,
+            "SELECT TOP (@p1) * FROM [users] WHERE [id] > @p2 ORDER BY [id]"
+        );
+        assert_eq!(stmt.params, vec![Value::Int(11), Value::Int(10)]);
+
+        // OFFSET requires ORDER BY
+        let query = SqlQueryBuilder::default().source("users").build()?;
+        assert_eq!(
+            query.to_statement(&d).sql,
+            "SELECT * FROM [users] ORDER BY (SELECT NULL) OFFSET @p1 ROWS FETCH NEXT @p2 ROWS ONLY"
+        );
+        assert_eq!(d.quote_identifier("a]b"), "[a]]b]");
+        Ok(())
+    }
+
+    #[test]
+    fn oracle_dialect_should_render() -> Result<()> {
+        let d = OracleDialect;
+        assert_eq!(
+            offset_query()?.to_statement(&d).sql,
+            r#"SELECT "id", "name" FROM "users" WHERE "active" = :p1 ORDER BY "name" NULLS LAST OFFSET :p2 ROWS FETCH NEXT :p3 ROWS ONLY"#
+        );
+        assert_eq!(
+            keyset_query()?.to_statement(&d).sql,
+            r#"SELECT * FROM "users" WHERE "id" > :p1 ORDER BY "id" FETCH FIRST :p2 ROWS ONLY"#
+        );
+        assert_eq!(d.literal(&false.into()), "0");
+        Ok(())
+    }
+
+    #[test]
+    fn oracle11_dialect_should_render() -> Result<()> {
+        let d = Oracle11Dialect;
+        let stmt = offset_query()?.to_statement(&d);
+        assert_eq!(
+            stmt.sql,
+            r#"SELECT * FROM (SELECT t.*, ROWNUM rn FROM (SELECT "id", "name" FROM "users" WHERE "active" = :p1 ORDER BY "name" NULLS LAST) t WHERE ROWNUM <= :p2) WHERE rn > :p3"#
+        );
+        assert_eq!(
+            stmt.params,
+            vec![Value::Bool(true), Value::Int(31), Value::Int(20)]
+        );
+        let stmt = keyset_query()?.to_statement(&d);
+        assert_eq!(
+            stmt.sql,
+            r#"SELECT * FROM (SELECT * FROM "users" WHERE "id" > :p1 ORDER BY "id") WHERE ROWNUM <= :p2"#
+        );
+        assert_eq!(stmt.params, vec![Value::Int(10), Value::Int(11)]);
+
+        let query = SqlQuery {
+            total: TotalPolicy::Capped { cap: 1000 },
+            ..offset_query()?
+        };
+        assert_eq!(
+            query.to_count_statement(&d).sql,
+            r#"SELECT COUNT(*) FROM (SELECT 1 AS one FROM "users" WHERE "active" = :p1 AND ROWNUM <= :p2) t"#
+        );
+        Ok(())
+    }
+}
@@ -5,8 +5,21 @@ pub(super) type Result<T, E = Error> = std::result::Result<T, E>;
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(crate)))]
 pub enum Error {
-    #[snafu(display("Page size must be between 1-99. Got: {size}"))]
-    InvalidPageSize { size: u64 },
+    #[snafu(display("Page size must be between {min}-{max}. Got: {size}"))]
+    InvalidPageSize { size: u64, min: u64, max: u64 },
+    #[snafu(display("Invalid page policy: sizes must satisfy 1 <= min <= default <= max"))]
+    InvalidPagePolicy,
+    #[snafu(display(
+        "Offset {offset} exceeds the maximum of {max}{}",
+        if *suggest_keyset { ". Use keyset pagination to go deeper" } else { "" }
+    ))]
+    OffsetTooDeep {
+        offset: u64,
+        max: u64,
+        suggest_keyset: bool,
+    },
+    #[snafu(display("Offset overflows past {offset} with a page size of {page_size}"))]
+    OffsetOverflow { offset: u64, page_size: u64 },
     #[snafu(display("Source cannot be empty"))]
     InvalidSource,
     #[snafu(display("Invalid base64 string: {}", s))]
@@ -14,11 +27,81 @@ pub enum Error {
         s: String,
         source: base64::DecodeSliceError,
     },
-    #[snafu(display("Invalid UTF-8 string"))]
-    InvalidUtf8 { source: std::str::Utf8Error },
-    #[snafu(display("Invalid number: {s}"))]
-    InvalidNumber {
-        s: String,
-        source: std::num::ParseIntError,
-    },
+    #[snafu(display("Invalid cursor: {cursor}"))]
+    InvalidCursor { cursor: String },
+    #[snafu(display("Invalid cursor signature: {cursor}"))]
+    InvalidSignature { cursor: String },
+    #[snafu(display("Failed to decrypt cursor: {cursor}"))]
+    CursorDecrypt { cursor: String },
+    #[snafu(display("Cursor was issued for a different query: {cursor}"))]
+    CursorMismatch { cursor: String },
+    #[snafu(display("Cursor has expired: {cursor}"))]
+    CursorExpired { cursor: String },
+    #[snafu(display("Secret of cursor key {id} must have at least {min} bytes"))]
+    WeakSecret { id: String, min: usize },
+    #[snafu(display("Invalid connection arguments: {reason}"))]
+    InvalidConnectionArgs { reason: String },
+    #[snafu(display("Cursors must be signed or encrypted to expire"))]
+    UnsignedTtl,
+    #[snafu(display("Keyset pagination requires a sort order"))]
+    InvalidKeyset,
+    #[snafu(display("Keyset pagination requires the sort keys of the rows"))]
+    MissingSortKey,
+    #[snafu(display("Invalid identifier: {ident}"))]
+    InvalidIdentifier { ident: String },
+    #[snafu(display("Invalid sort order: {order}"))]
+    InvalidOrder { order: String },
+    #[snafu(display("Invalid value for query parameter {param}: {value}"))]
+    InvalidParam { param: String, value: String },
+    #[snafu(display("Field cannot be sorted: {field}"))]
+    UnsortableField { field: String },
+    #[snafu(display("Field cannot be filtered: {field}"))]
+    UnfilterableField { field: String },
+    #[snafu(display("Unknown filter operator: {op}"))]
+    UnknownOperator { op: String },
+}
+
+impl Error {
+    /// machine readable code of the error, e.g. for the extensions of a GraphQL error. Cursor
+    /// errors share a code so clients can't probe how cursors are verified
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidPageSize { .. } => "INVALID_PAGE_SIZE",
+            Error::InvalidPagePolicy => "INVALID_PAGE_POLICY",
+            Error::OffsetTooDeep { .. } => "OFFSET_TOO_DEEP",
+            Error::OffsetOverflow { .. } => "OFFSET_OVERFLOW",
+            Error::InvalidSource => "INVALID_SOURCE",
+            Error::Base64Decode { .. }
+            | Error::InvalidCursor { .. }
+            | Error::InvalidSignature { .. }
+            | Error::CursorDecrypt { .. } => "INVALID_CURSOR",
+            Error::CursorMismatch { .. } => "CURSOR_MISMATCH",
+            Error::CursorExpired { .. } => "CURSOR_EXPIRED",
+            Error::WeakSecret { .. } => "WEAK_SECRET",
+            Error::InvalidConnectionArgs { .. } => "INVALID_CONNECTION_ARGS",
+            Error::UnsignedTtl => "UNSIGNED_TTL",
+            Error::InvalidKeyset => "INVALID_KEYSET",
+            Error::MissingSortKey => "MISSING_SORT_KEY",
+            Error::InvalidIdentifier { .. } => "INVALID_IDENTIFIER",
+            Error::InvalidOrder { .. } => "INVALID_ORDER",
+            Error::InvalidParam { .. } => "INVALID_PARAM",
+            Error::UnsortableField { .. } => "UNSORTABLE_FIELD",
+            Error::UnfilterableField { .. } => "UNFILTERABLE_FIELD",
+            Error::UnknownOperator { .. } => "UNKNOWN_OPERATOR",
+        }
+    }
+
+    /// message of the error for clients. Cursor errors neither echo the cursor nor tell why it
+    /// was rejected
+    pub fn client_message(&self) -> String {
+        match self {
+            Error::Base64Decode { .. }
+            | Error::InvalidCursor { .. }
+            | Error::InvalidSignature { .. }
+            | Error::CursorDecrypt { .. } => "Invalid cursor".to_owned(),
+            Error::CursorMismatch { .. } => "Cursor was issued for a different query".to_owned(),
+            Error::CursorExpired { .. } => "Cursor has expired".to_owned(),
+            _ => self.to_string(),
+        }
+    }
 }
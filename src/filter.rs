@@ -0,0 +1,351 @@
+use crate::{error::*, statement::Binder, Ident, Value};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, ops::Not};
+
+/// A filter condition (the WHERE clause). Values are rendered as bind parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Filter<'a> {
+    Eq {
+        field: Ident<'a>,
+        value: Value,
+    },
+    Ne {
+        field: Ident<'a>,
+        value: Value,
+    },
+    Lt {
+        field: Ident<'a>,
+        value: Value,
+    },
+    Le {
+        field: Ident<'a>,
+        value: Value,
+    },
+    Gt {
+        field: Ident<'a>,
+        value: Value,
+    },
+    Ge {
+        field: Ident<'a>,
+        value: Value,
+    },
+    In {
+        field: Ident<'a>,
+        values: Vec<Value>,
+    },
+    Between {
+        field: Ident<'a>,
+        low: Value,
+        high: Value,
+    },
+    Like {
+        field: Ident<'a>,
+        pattern: Value,
+    },
+    IsNull {
+        field: Ident<'a>,
+    },
+    IsNotNull {
+        field: Ident<'a>,
+    },
+    And {
+        filters: Vec<Filter<'a>>,
+    },
+    Or {
+        filters: Vec<Filter<'a>>,
+    },
+    Not {
+        filter: Box<Filter<'a>>,
+    },
+    /// raw SQL condition emitted verbatim. Like raw fields (see `Ident::Raw`) it is never
+    /// deserialized, so clients can't send it
+    #[serde(skip_deserializing)]
+    Raw {
+        sql: Cow<'a, str>,
+    },
+}
+
+impl<'a> Filter<'a> {
+    pub fn eq(field: impl Into<Ident<'a>>, value: impl Into<Value>) -> Self {
+        Self::Eq {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn ne(field: impl Into<Ident<'a>>, value: impl Into<Value>) -> Self {
+        Self::Ne {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn lt(field: impl Into<Ident<'a>>, value: impl Into<Value>) -> Self {
+        Self::Lt {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn le(field: impl Into<Ident<'a>>, value: impl Into<Value>) -> Self {
+        Self::Le {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn gt(field: impl Into<Ident<'a>>, value: impl Into<Value>) -> Self {
+        Self::Gt {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn ge(field: impl Into<Ident<'a>>, value: impl Into<Value>) -> Self {
+        Self::Ge {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn in_list<V: Into<Value>>(
+        field: impl Into<Ident<'a>>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Self::In {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn between(
+        field: impl Into<Ident<'a>>,
+        low: impl Into<Value>,
+        high: impl Into<Value>,
+    ) -> Self {
+        Self::Between {
+            field: field.into(),
+            low: low.into(),
+            high: high.into(),
+        }
+    }
+
+    pub fn like(field: impl Into<Ident<'a>>, pattern: impl Into<Value>) -> Self {
+        Self::Like {
+            field: field.into(),
+            pattern: pattern.into(),
+        }
+    }
+
+    pub fn is_null(field: impl Into<Ident<'a>>) -> Self {
+        Self::IsNull {
+            field: field.into(),
+        }
+    }
+
+    pub fn is_not_null(field: impl Into<Ident<'a>>) -> Self {
+        Self::IsNotNull {
+            field: field.into(),
+        }
+    }
+
+    /// opt in to a raw SQL condition. Never build it from user input
+    pub fn raw(sql: impl Into<Cow<'a, str>>) -> Self {
+        Self::Raw { sql: sql.into() }
+    }
+
+    /// combine with another filter, both must match (e.g. a user filter and a tenant filter)
+    pub fn and(self, other: Filter<'a>) -> Self {
+        match self {
+            Self::And { mut filters } => {
+                filters.push(other);
+                Self::And { filters }
+            }
+            this => Self::And {
+                filters: vec![this, other],
+            },
+        }
+    }
+
+    /// combine with another filter, either must match
+    pub fn or(self, other: Filter<'a>) -> Self {
+        match self {
+            Self::Or { mut filters } => {
+                filters.push(other);
+                Self::Or { filters }
+            }
+            this => Self::Or {
+                filters: vec![this, other],
+            },
+        }
+    }
+
+    /// validate all the fields referenced by the filter
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Eq { field, .. }
+            | Self::Ne { field, .. }
+            | Self::Lt { field, .. }
+            | Self::Le { field, .. }
+            | Self::Gt { field, .. }
+            | Self::Ge { field, .. }
+            | Self::In { field, .. }
+            | Self::Between { field, .. }
+            | Self::Like { field, .. }
+            | Self::IsNull { field }
+            | Self::IsNotNull { field } => field.validate(),
+            Self::And { filters } | Self::Or { filters } => {
+                filters.iter().try_for_each(Filter::validate)
+            }
+            Self::Not { filter } => filter.validate(),
+            Self::Raw { .. } => Ok(()),
+        }
+    }
+
+    pub(crate) fn to_sql(&self, binder: &mut Binder) -> String {
+        let compare = |field: &Ident, op: &str, value: &Value, binder: &mut Binder| {
+            format!(
+                "{} {op} {}",
+                field.to_sql(binder.dialect),
+                binder.bind(value)
+            )
+        };
+
+        match self {
+            Self::Eq { field, value } if value.is_null() => {
+                format!("{} IS NULL", field.to_sql(binder.dialect))
+            }
+            Self::Ne { field, value } if value.is_null() => {
+                format!("{} IS NOT NULL", field.to_sql(binder.dialect))
+            }
+            Self::Eq { field, value } => compare(field, "=", value, binder),
+            Self::Ne { field, value } => compare(field, "<>", value, binder),
+            Self::Lt { field, value } => compare(field, "<", value, binder),
+            Self::Le { field, value } => compare(field, "<=", value, binder),
+            Self::Gt { field, value } => compare(field, ">", value, binder),
+            Self::Ge { field, value } => compare(field, ">=", value, binder),
+            Self::Like { field, pattern } => compare(field, "LIKE", pattern, binder),
+            Self::In { values, .. } if values.is_empty() => "1 = 0".to_owned(),
+            Self::In { field, values } => {
+                let field = field.to_sql(binder.dialect);
+                let values = values.iter().map(|v| binder.bind(v)).join(", ");
+                format!("{field} IN ({values})")
+            }
+            Self::Between { field, low, high } => {
+                let field = field.to_sql(binder.dialect);
+                let low = binder.bind(low);
+                format!("{field} BETWEEN {low} AND {}", binder.bind(high))
+            }
+            Self::IsNull { field } => format!("{} IS NULL", field.to_sql(binder.dialect)),
+            Self::IsNotNull { field } => format!("{} IS NOT NULL", field.to_sql(binder.dialect)),
+            Self::And { filters } if filters.is_empty() => "1 = 1".to_owned(),
+            Self::Or { filters } if filters.is_empty() => "1 = 0".to_owned(),
+            Self::And { filters } => filters.iter().map(|f| f.to_operand(binder)).join(" AND "),
+            Self::Or { filters } => filters.iter().map(|f| f.to_operand(binder)).join(" OR "),
+            Self::Not { filter } => format!("NOT ({})", filter.to_sql(binder)),
+            Self::Raw { sql } => sql.to_string(),
+        }
+    }
+
+    /// render the filter as an operand of AND/OR, parenthesized if needed
+    pub(crate) fn to_operand(&self, binder: &mut Binder) -> String {
+        match self {
+            Self::And { filters } | Self::Or { filters } if filters.len() > 1 => {
+                format!("({})", self.to_sql(binder))
+            }
+            Self::Raw { .. } => format!("({})", self.to_sql(binder)),
+            _ => self.to_sql(binder),
+        }
+    }
+}
+
+impl<'a> Not for Filter<'a> {
+    type Output = Filter<'a>;
+
+    fn not(self) -> Self::Output {
+        Filter::Not {
+            filter: Box::new(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GenericDialect, Placeholder, PostgresDialect};
+
+    #[test]
+    fn filter_should_render_with_params() {
+        let filter = Filter::eq("status", "active")
+            .and(Filter::in_list("role", ["admin", "owner"]))
+            .and(Filter::between("age", 18, 65))
+            .and(Filter::like("name", "t%").or(Filter::is_null("name")))
+            .and(!Filter::ge("score", 1.5));
+
+        let mut binder = Binder::params(&PostgresDialect);
+        let sql = filter.to_sql(&mut binder);
+        assert_eq!(
+            sql,
+            r#""status" = $1 AND "role" IN ($2, $3) AND "age" BETWEEN $4 AND $5 AND ("name" LIKE $6 OR "name" IS NULL) AND NOT ("score" >= $7)"#
+        );
+        let stmt = binder.finish(sql);
+        assert_eq!(
+            stmt.params,
+            vec![
+                "active".into(),
+                "admin".into(),
+                "owner".into(),
+                18.into(),
+                65.into(),
+                "t%".into(),
+                1.5.into()
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_should_render_edge_cases() {
+        let dialect = GenericDialect(Placeholder::Question);
+        let mut binder = Binder::inline(&dialect);
+        assert_eq!(
+            Filter::eq("deleted_at", Value::Null).to_sql(&mut binder),
+            "deleted_at IS NULL"
+        );
+        assert_eq!(
+            Filter::ne("name", "o'neil").to_sql(&mut binder),
+            "name <> 'o''neil'"
+        );
+        assert_eq!(
+            Filter::in_list("id", Vec::<i64>::new()).to_sql(&mut binder),
+            "1 = 0"
+        );
+        assert_eq!(
+            Filter::raw("a = 1 OR b = 2")
+                .and(Filter::lt("c", 3))
+                .to_sql(&mut binder),
+            "(a = 1 OR b = 2) AND c < 3"
+        );
+    }
+
+    #[test]
+    fn filter_should_serialize() -> anyhow::Result<()> {
+        let filter = Filter::eq("status", "active").or(Filter::gt("age", 18));
+        let json = serde_json::to_string(&filter)?;
+        assert_eq!(
+            json,
+            r#"{"op":"or","filters":[{"op":"eq","field":"status","value":"active"},{"op":"gt","field":"age","value":18}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Filter>(&json)?, filter);
+
+        // raw SQL can't be smuggled in by clients
+        assert!(serde_json::from_str::<Filter>(r#"{"op":"raw","sql":"1 = 1"}"#).is_err());
+        assert!(serde_json::from_str::<Filter>(
+            r#"{"op":"eq","field":{"raw":"1 = 1 OR id"},"value":1}"#
+        )
+        .is_err());
+        assert!(Filter::eq("id; --", 1).validate().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{error::*, Connection, ConnectionArgs, SortKey, SqlQuery};
+use async_graphql::{connection, ErrorExtensions, OutputType};
+use snafu::OptionExt;
+
+/// The async-graphql connection of a page, with opaque string cursors
+pub type GraphqlConnection<T> = connection::Connection<String, T>;
+
+impl ConnectionArgs {
+    /// build the arguments from the ones of a connection field, which are GraphQL `Int`s
+    pub fn from_graphql(
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Self> {
+        let count = |n: Option<i32>| {
+            n.map(u64::try_from)
+                .transpose()
+                .ok()
+                .context(InvalidConnectionArgsSnafu {
+                    reason: "first and last cannot be negative",
+                })
+        };
+        Ok(Self {
+            first: count(first)?,
+            after,
+            last: count(last)?,
+            before,
+        })
+    }
+}
+
+impl<'a> SqlQuery<'a> {
+    /// build the async-graphql connection from the fetched rows (page_size + 1 rows, see
+    /// `connection`)
+    pub fn graphql_connection<T: OutputType>(&self, rows: Vec<T>) -> Result<GraphqlConnection<T>> {
+        self.connection(rows).map(Into::into)
+    }
+
+    /// like `graphql_connection`, but supports keyset mode too (see `keyset_connection`)
+    pub fn graphql_keyset_connection<T>(&self, rows: Vec<T>) -> Result<GraphqlConnection<T>>
+    where
+        T: SortKey + OutputType,
+    {
+        self.keyset_connection(rows).map(Into::into)
+    }
+}
+
+impl<T: OutputType> From<Connection<T>> for GraphqlConnection<T> {
+    fn from(value: Connection<T>) -> Self {
+        let info = value.page_info;
+        let mut connection =
+            connection::Connection::new(info.has_previous_page, info.has_next_page);
+        connection.edges.extend(
+            value
+                .edges
+                .into_iter()
+                .map(|edge| connection::Edge::new(edge.cursor, edge.node)),
+        );
+        connection
+    }
+}
+
+impl ErrorExtensions for Error {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.client_message()).extend_with(|_, e| {
+            e.set("code", self.code());
+            match self {
+                Error::InvalidPageSize { min, max, .. } => {
+                    e.set("min", *min);
+                    e.set("max", *max);
+                }
+                Error::OffsetTooDeep { max, .. } => e.set("max", *max),
+                _ => {}
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrderBy, PageMode, Placeholder, SqlQueryBuilder};
+    use async_graphql::Value;
+
+    #[derive(async_graphql::SimpleObject)]
+    struct User {
+        id: i64,
+    }
+
+    impl SortKey for User {
+        fn sort_key(&self, _column: &str) -> crate::Value {
+            self.id.into()
+        }
+    }
+
+    #[test]
+    fn query_should_build_graphql_connection() -> anyhow::Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![OrderBy::asc("id").not_null()])
+            .mode(PageMode::Keyset)
+            .build()?;
+        let args = ConnectionArgs::from_graphql(None, None, Some(2), None)?;
+        let query = query.with_connection_args(&args)?;
+
+        let rows = (1..=3).map(|id| User { id }).collect();
+        let connection = query.graphql_keyset_connection(rows)?;
+        assert!(connection.has_next_page);
+        assert!(!connection.has_previous_page);
+        assert_eq!(connection.edges.len(), 2);
+        assert_eq!(connection.edges[1].node.id, 2);
+
+        let after = connection.edges[1].cursor.clone();
+        let query = query.with_connection_args(&ConnectionArgs::forward(2, Some(after)))?;
+        let stmt = query.to_sql_with_params(Placeholder::Dollar);
+        assert_eq!(
+            stmt.sql,
+            "SELECT * FROM users WHERE id > $1 ORDER BY id LIMIT $2"
+        );
+        assert_eq!(stmt.params, vec![2.into(), crate::Value::Int(3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn error_should_extend_with_code() {
+        let err = ConnectionArgs::from_graphql(None, None, Some(-1), None).unwrap_err();
+        let err = err.extend();
+        assert_eq!(
+            err.message,
+            "Invalid connection arguments: first and last cannot be negative"
+        );
+        let extensions = err.extensions.unwrap();
+        assert_eq!(
+            extensions.get("code"),
+            Some(&Value::from("INVALID_CONNECTION_ARGS"))
+        );
+
+        let err = Error::InvalidPageSize {
+            size: 0,
+            min: 1,
+            max: 100,
+        }
+        .extend();
+        let extensions = err.extensions.unwrap();
+        assert_eq!(
+            extensions.get("code"),
+            Some(&Value::from("INVALID_PAGE_SIZE"))
+        );
+        assert_eq!(extensions.get("max"), Some(&Value::from(100)));
+
+        // the cursor is not echoed back
+        let err = SqlQueryBuilder::default()
+            .source("users")
+            .cursor("secret-looking-cursor")
+            .build()
+            .unwrap_err()
+            .extend();
+        assert_eq!(err.message, "Invalid cursor");
+        assert_eq!(
+            err.extensions.unwrap().get("code"),
+            Some(&Value::from("INVALID_CURSOR"))
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{error::*, Dialect};
+use itertools::Itertools;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use snafu::ensure;
+use std::{borrow::Cow, fmt};
+
+/// A table, view or column name, or a raw SQL expression. Only names are deserialized, and they
+/// are validated (see `validate_projection`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Ident<'a> {
+    /// identifier like `users`, `public.users` or `users.id`. It is validated and quoted per dialect
+    Name(Cow<'a, str>),
+    /// raw SQL expression emitted verbatim. Never build it from user input, it is never
+    /// deserialized
+    Raw { raw: Cow<'a, str> },
+}
+
+impl<'a> Ident<'a> {
+    /// opt in to emit the expression verbatim, e.g. `count(*)` or a subquery
+    pub fn raw(expr: impl Into<Cow<'a, str>>) -> Self {
+        Self::Raw { raw: expr.into() }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Ident::Name(name) => name,
+            Ident::Raw { raw } => raw,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.as_str().is_empty()
+    }
+
+    /// make sure a name only contains valid identifier parts separated by `.`, e.g. for a
+    /// source or a column. Raw expressions are not checked
+    pub fn validate(&self) -> Result<()> {
+        self.check(false)
+    }
+
+    /// make sure a projection item is a valid name, whose last part can also be `*` (e.g.
+    /// `users.*`). Raw expressions are not checked
+    pub fn validate_projection(&self) -> Result<()> {
+        self.check(true)
+    }
+
+    fn check(&self, allow_star: bool) -> Result<()> {
+        let Ident::Name(name) = self else {
+            return Ok(());
+        };
+
+        let mut parts = name.split('.').peekable();
+        while let Some(part) = parts.next() {
+            let is_last = parts.peek().is_none();
+            ensure!(
+                is_valid_part(part) || (allow_star && is_last && part == "*"),
+                InvalidIdentifierSnafu {
+                    ident: name.as_ref()
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// render the identifier, quoting each part of a name with the dialect
+    pub fn to_sql(&self, dialect: &dyn Dialect) -> String {
+        match self {
+            Ident::Name(name) => name
+                .split('.')
+                .map(|part| match part {
+                    "*" => Cow::Borrowed(part),
+                    _ => Cow::Owned(dialect.quote_identifier(part)),
+                })
+                .join("."),
+            Ident::Raw { raw } => raw.to_string(),
+        }
+    }
+}
+
+fn is_valid_part(part: &str) -> bool {
+    let mut chars = part.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+impl<'de> Deserialize<'de> for Ident<'_> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ident = Ident::Name(String::deserialize(deserializer)?.into());
+        ident.validate_projection().map_err(D::Error::custom)?;
+        Ok(ident)
+    }
+}
+
+impl Default for Ident<'_> {
+    fn default() -> Self {
+        Ident::Name(Cow::Borrowed(""))
+    }
+}
+
+impl fmt::Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl<'a> From<&'a str> for Ident<'a> {
+    fn from(name: &'a str) -> Self {
+        Ident::Name(name.into())
+    }
+}
+
+impl From<String> for Ident<'_> {
+    fn from(name: String) -> Self {
+        Ident::Name(name.into())
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Ident<'a> {
+    fn from(name: Cow<'a, str>) -> Self {
+        Ident::Name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MsSqlDialect, PostgresDialect};
+
+    #[test]
+    fn ident_should_validate_names() {
+        for name in ["users", "public.users", "users.id", "_tmp$1"] {
+            assert!(Ident::from(name).validate().is_ok(), "{name}");
+        }
+        // `*` only selects columns
+        for name in ["users.*", "*"] {
+            assert!(Ident::from(name).validate().is_err(), "{name}");
+            assert!(Ident::from(name).validate_projection().is_ok(), "{name}");
+        }
+        for name in [
+            "",
+            "id; DROP TABLE users",
+            "1users",
+            "users.",
+            "a.*.b",
+            "na-me",
+        ] {
+            assert!(Ident::from(name).validate().is_err(), "{name}");
+        }
+        assert!(Ident::raw("count(*) AS total").validate().is_ok());
+        assert!(Ident::from("a.*.b").validate_projection().is_err());
+    }
+
+    #[test]
+    fn ident_should_not_deserialize_raw_sql() {
+        let ident: Result<Ident, _> = serde_json::from_str(r#"{"raw": "1; DROP TABLE users"}"#);
+        assert!(ident.is_err());
+        assert_eq!(
+            serde_json::from_str::<Ident>(r#""users.id""#).ok(),
+            Some(Ident::from("users.id"))
+        );
+        // nor invalid names
+        let ident = serde_json::from_str::<Ident>(r#""users; DROP TABLE users; --""#);
+        assert!(ident.is_err());
+    }
+
+    #[test]
+    fn ident_should_quote_per_dialect() {
+        let ident = Ident::from("public.users");
+        assert_eq!(ident.to_sql(&PostgresDialect), r#""public"."users""#);
+        assert_eq!(ident.to_sql(&MsSqlDialect), "[public].[users]");
+        assert_eq!(Ident::from("u.*").to_sql(&PostgresDialect), r#""u".*"#);
+        assert_eq!(Ident::raw("count(*)").to_sql(&PostgresDialect), "count(*)");
+    }
+}
@@ -1,8 +1,38 @@
+mod codec;
+mod connection;
+mod cursor;
+mod dialect;
 mod error;
+mod filter;
+#[cfg(feature = "async-graphql")]
+mod graphql;
+mod ident;
+mod links;
+mod order;
 mod pager;
+mod resource;
 mod sql;
+mod statement;
 mod utils;
+mod value;
+#[cfg(feature = "axum")]
+mod web;
 
+pub use codec::{Clock, CursorCodec, SystemClock};
+pub use connection::{Connection, ConnectionArgs, ConnectionPageInfo, Edge};
+pub use cursor::{Cursor, CursorEnvelope};
+pub use dialect::*;
 pub use error::Error;
+pub use filter::Filter;
+#[cfg(feature = "async-graphql")]
+pub use graphql::GraphqlConnection;
+pub use ident::Ident;
+pub use links::{HalLink, HalLinks, LinkBuilder, PageLinks};
+pub use order::{Direction, Nulls, OrderBy};
 pub use pager::*;
+pub use resource::{FieldType, Resource, ResourceBuilder};
 pub use sql::*;
+pub use statement::{Placeholder, Statement};
+pub use value::Value;
+#[cfg(feature = "axum")]
+pub use web::{Page, PageRequest};
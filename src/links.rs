@@ -0,0 +1,251 @@
+use crate::Pager;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// name of the query parameter carrying the cursor, as parsed by `Resource`
+const CURSOR_PARAM: &str = "cursor";
+
+/// Build the navigation links of a page from the URL of the request, e.g. for a `Link` header
+/// (RFC 8288) or the links object of a JSON:API or HAL document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkBuilder<'a> {
+    /// URL the query string is appended to, e.g. `https://api.example.com/users`
+    base_url: Cow<'a, str>,
+    /// query parameters of the current request, other than the cursor
+    params: Vec<(String, String)>,
+    /// cursor of the current request
+    cursor: Option<String>,
+}
+
+/// Links to the current, first, previous, next and last pages. Missing links are serialized as
+/// `null`, as in the links object of JSON:API
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub first: Option<String>,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub last: Option<String>,
+}
+
+/// The `_links` object of a HAL document, missing links are omitted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HalLinks {
+    #[serde(rename = "self")]
+    pub current: HalLink,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first: Option<HalLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<HalLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<HalLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last: Option<HalLink>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HalLink {
+    pub href: String,
+}
+
+impl<'a> LinkBuilder<'a> {
+    pub fn new(base_url: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// keep the parameters of the query string of the current request (with or without the
+    /// leading `?`), e.g. the page size, sort order and filters
+    pub fn with_query(self, query: &str) -> Self {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        self.with_params(form_urlencoded::parse(query.as_bytes()))
+    }
+
+    /// keep the decoded parameters of the current request
+    pub fn with_params<K, V>(mut self, params: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (key, value) in params {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            match key {
+                CURSOR_PARAM => self.cursor = Some(value.to_owned()),
+                _ => self.params.push((key.to_owned(), value.to_owned())),
+            }
+        }
+        self
+    }
+
+    /// the links of a pager with encoded cursors, e.g. mapped with `SqlQuery::encode_cursor`
+    pub fn links(&self, pager: &Pager<String>) -> PageLinks {
+        let link = |cursor: &Option<String>| cursor.as_deref().map(|c| self.url(Some(c)));
+        PageLinks {
+            current: self.url(self.cursor.as_deref()),
+            first: link(&pager.first),
+            prev: link(&pager.prev),
+            next: link(&pager.next),
+            last: link(&pager.last),
+        }
+    }
+
+    fn url(&self, cursor: Option<&str>) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.extend_pairs(&self.params);
+        if let Some(cursor) = cursor {
+            query.append_pair(CURSOR_PARAM, cursor);
+        }
+        let query = query.finish();
+
+        let base = self.base_url.trim_end_matches(['?', '&']);
+        match (query.is_empty(), base.contains('?')) {
+            (true, _) => base.to_owned(),
+            (false, true) => format!("{base}&{query}"),
+            (false, false) => format!("{base}?{query}"),
+        }
+    }
+}
+
+impl PageLinks {
+    /// render the value of a `Link` header (RFC 8288), e.g.
+    /// `<https://api.example.com/users?cursor=...>; rel="next"`
+    pub fn to_link_header(&self) -> String {
+        self.relations()
+            .map(|(rel, url)| format!("<{url}>; rel=\"{rel}\""))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// the links as the `_links` object of a HAL document
+    pub fn to_hal(&self) -> HalLinks {
+        let href = |url: &Option<String>| url.clone().map(|href| HalLink { href });
+        HalLinks {
+            current: HalLink {
+                href: self.current.clone(),
+            },
+            first: href(&self.first),
+            prev: href(&self.prev),
+            next: href(&self.next),
+            last: href(&self.last),
+        }
+    }
+
+    fn relations(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("first", &self.first),
+            ("prev", &self.prev),
+            ("next", &self.next),
+            ("last", &self.last),
+        ]
+        .into_iter()
+        .filter_map(|(rel, url)| url.as_deref().map(|url| (rel, url)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pager::pager_test_utils::generate_test_ids, Cursor, SqlQueryBuilder};
+    use anyhow::Result;
+    use serde_json::json;
+
+    fn pager() -> Pager<String> {
+        Pager {
+            prev: Some("p".into()),
+            next: Some("n/1+".into()),
+            first: Some("f".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn links_should_render_link_header() {
+        let links = LinkBuilder::new("https://api.example.com/users")
+            .with_query("?page_size=20&cursor=c&filter[status]=active")
+            .links(&pager());
+        assert_eq!(
+            links.current,
+            "https://api.example.com/users?page_size=20&filter%5Bstatus%5D=active&cursor=c"
+        );
+        assert_eq!(
+            links.to_link_header(),
+            "<https://api.example.com/users?page_size=20&filter%5Bstatus%5D=active&cursor=f>; rel=\"first\", \
+             <https://api.example.com/users?page_size=20&filter%5Bstatus%5D=active&cursor=p>; rel=\"prev\", \
+             <https://api.example.com/users?page_size=20&filter%5Bstatus%5D=active&cursor=n%2F1%2B>; rel=\"next\""
+        );
+
+        // base URLs with a query string, no cursor on the first page
+        let links = LinkBuilder::new("/users?v=2").links(&Pager {
+            next: Some("n".into()),
+            ..Default::default()
+        });
+        assert_eq!(links.current, "/users?v=2");
+        assert_eq!(
+            links.to_link_header(),
+            "</users?v=2&cursor=n>; rel=\"next\""
+        );
+    }
+
+    #[test]
+    fn links_should_serialize_for_json_api_and_hal() -> Result<()> {
+        let links = LinkBuilder::new("/users").links(&pager());
+        assert_eq!(
+            serde_json::to_value(&links)?,
+            json!({
+                "self": "/users",
+                "first": "/users?cursor=f",
+                "prev": "/users?cursor=p",
+                "next": "/users?cursor=n%2F1%2B",
+                "last": null,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(links.to_hal())?,
+            json!({
+                "self": { "href": "/users" },
+                "first": { "href": "/users?cursor=f" },
+                "prev": { "href": "/users?cursor=p" },
+                "next": { "href": "/users?cursor=n%2F1%2B" },
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn links_should_carry_cursors_of_query() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .page_size(2u64)
+            .build()?;
+        let pager = query
+            .get_pager(&mut generate_test_ids(1, 3))?
+            .map(|cursor| query.encode_cursor(&cursor));
+        let links = LinkBuilder::new("/users")
+            .with_params([("page_size", "2")])
+            .links(&pager);
+
+        let next = links.next.unwrap_or_default();
+        let (_, cursor) =
+            form_urlencoded::parse(next.split_once('?').unwrap_or_default().1.as_bytes())
+                .find(|(key, _)| key == CURSOR_PARAM)
+                .unwrap_or_default();
+        assert_eq!(query.decode_cursor(&cursor)?, Cursor::Offset(2));
+        assert_eq!(links.last, None);
+
+        // an exact total gives the last page in offset mode
+        let pager = query
+            .pager_with_total(query.get_pager(&mut generate_test_ids(1, 3))?, 7)
+            .map(|cursor| query.encode_cursor(&cursor));
+        let links = LinkBuilder::new("/users").links(&pager);
+        let last = links.last.unwrap_or_default();
+        let (_, cursor) =
+            form_urlencoded::parse(last.split_once('?').unwrap_or_default().1.as_bytes())
+                .find(|(key, _)| key == CURSOR_PARAM)
+                .unwrap_or_default();
+        assert_eq!(query.decode_cursor(&cursor)?, Cursor::Offset(6));
+        Ok(())
+    }
+}
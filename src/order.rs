@@ -0,0 +1,377 @@
+use crate::{error::*, statement::Binder, Dialect, Ident, Value};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt};
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+/// One column of the sort order (an item of the ORDER BY clause)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderBy<'a> {
+    /// column to sort by
+    pub column: Ident<'a>,
+    /// sort direction
+    #[serde(default)]
+    pub direction: Direction,
+    /// position of NULL values, the default of the dialect if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nulls: Option<Nulls>,
+    /// the column is never NULL, so keyset cursors don't need to account for NULLs and can seek
+    /// with a plain comparison
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub not_null: bool,
+}
+
+impl<'a> OrderBy<'a> {
+    pub fn asc(column: impl Into<Ident<'a>>) -> Self {
+        Self {
+            column: column.into(),
+            direction: Direction::Asc,
+            nulls: None,
+            not_null: false,
+        }
+    }
+
+    pub fn desc(column: impl Into<Ident<'a>>) -> Self {
+        Self {
+            column: column.into(),
+            direction: Direction::Desc,
+            nulls: None,
+            not_null: false,
+        }
+    }
+
+    pub fn nulls_first(mut self) -> Self {
+        self.nulls = Some(Nulls::First);
+        self
+    }
+
+    pub fn nulls_last(mut self) -> Self {
+        self.nulls = Some(Nulls::Last);
+        self
+    }
+
+    pub fn not_null(mut self) -> Self {
+        self.not_null = true;
+        self
+    }
+
+    /// the same column sorted the opposite way, used to page backwards
+    pub fn reversed(&self) -> Self {
+        Self {
+            column: self.column.clone(),
+            direction: match self.direction {
+                Direction::Asc => Direction::Desc,
+                Direction::Desc => Direction::Asc,
+            },
+            nulls: self.nulls.map(|nulls| match nulls {
+                Nulls::First => Nulls::Last,
+                Nulls::Last => Nulls::First,
+            }),
+            not_null: self.not_null,
+        }
+    }
+
+    /// whether NULLs come after all other values. When not specified this depends on the dialect
+    fn nulls_last_in_scan(&self, dialect: &dyn Dialect) -> bool {
+        match (self.nulls, self.direction) {
+            (Some(nulls), _) => nulls == Nulls::Last,
+            (None, Direction::Asc) => dialect.nulls_largest(),
+            (None, Direction::Desc) => !dialect.nulls_largest(),
+        }
+    }
+
+    /// whether any value of this column can come strictly after `value`
+    fn has_after(&self, value: &Value, dialect: &dyn Dialect) -> bool {
+        !(value.is_null() && self.nulls_last_in_scan(dialect))
+    }
+
+    /// predicate matching rows of this column that come strictly after `value`
+    fn after(&self, value: &Value, binder: &mut Binder) -> String {
+        let op = match self.direction {
+            Direction::Asc => ">",
+            Direction::Desc => "<",
+        };
+        let column = self.column.to_sql(binder.dialect);
+        match (value.is_null(), self.nulls_last_in_scan(binder.dialect)) {
+            (true, _) => format!("{column} IS NOT NULL"),
+            // nullable column with NULLs at the end, by default or not
+            (false, true) if !self.not_null => {
+                format!("({column} {op} {} OR {column} IS NULL)", binder.bind(value))
+            }
+            (false, _) => format!("{column} {op} {}", binder.bind(value)),
+        }
+    }
+
+    fn equals(&self, value: &Value, binder: &mut Binder) -> String {
+        let column = self.column.to_sql(binder.dialect);
+        if value.is_null() {
+            format!("{column} IS NULL")
+        } else {
+            format!("{column} = {}", binder.bind(value))
+        }
+    }
+
+    /// render the ORDER BY item, emulating NULLS FIRST/LAST if the dialect doesn't support it
+    fn to_sql(&self, dialect: &dyn Dialect) -> String {
+        let column = self.column.to_sql(dialect);
+        let Some(nulls) = self.nulls else {
+            return self.render(&column, None);
+        };
+        if dialect.supports_nulls_ordering() {
+            return self.render(&column, Some(nulls));
+        }
+
+        let nulls_last = nulls == Nulls::Last;
+        let native_nulls_last = Self {
+            nulls: None,
+            ..self.clone()
+        }
+        .nulls_last_in_scan(dialect);
+        if nulls_last == native_nulls_last {
+            self.render(&column, None)
+        } else {
+            let (null, non_null) = if nulls_last { (1, 0) } else { (0, 1) };
+            format!(
+                "CASE WHEN {column} IS NULL THEN {null} ELSE {non_null} END, {}",
+                self.render(&column, None)
+            )
+        }
+    }
+
+    fn render(&self, column: &str, nulls: Option<Nulls>) -> String {
+        let direction = match self.direction {
+            Direction::Asc => "",
+            Direction::Desc => " DESC",
+        };
+        let nulls = match nulls {
+            Some(Nulls::First) => " NULLS FIRST",
+            Some(Nulls::Last) => " NULLS LAST",
+            None => "",
+        };
+        format!("{column}{direction}{nulls}")
+    }
+}
+
+impl<'a> fmt::Display for OrderBy<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(self.column.as_str(), self.nulls))
+    }
+}
+
+impl FromStr for OrderBy<'static> {
+    type Err = Error;
+
+    /// parse an ORDER BY item like `created_at DESC NULLS LAST`
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split_whitespace();
+        let column = parts.next().context(InvalidOrderSnafu { order: s })?;
+        let mut order = OrderBy::asc(column.to_owned());
+        let rest = parts.map(|p| p.to_ascii_uppercase()).collect::<Vec<_>>();
+        let mut rest = rest.iter().map(String::as_str);
+        let mut next = rest.next();
+        match next {
+            Some("ASC") => next = rest.next(),
+            Some("DESC") => {
+                order.direction = Direction::Desc;
+                next = rest.next();
+            }
+            _ => {}
+        }
+        match (next, rest.next()) {
+            (None, _) => {}
+            (Some("NULLS"), Some("FIRST")) => order = order.nulls_first(),
+            (Some("NULLS"), Some("LAST")) => order = order.nulls_last(),
+            _ => return InvalidOrderSnafu { order: s }.fail(),
+        }
+        ensure!(rest.next().is_none(), InvalidOrderSnafu { order: s });
+
+        Ok(order)
+    }
+}
+
+/// render the ORDER BY items
+pub(crate) fn order_clause(order: &[OrderBy], dialect: &dyn Dialect) -> String {
+    order.iter().map(|o| o.to_sql(dialect)).join(", ")
+}
+
+/// the WHERE predicate matching rows strictly after the sort key `values` in the given order
+pub(crate) fn seek_predicate(order: &[OrderBy], values: &[Value], binder: &mut Binder) -> String {
+    let same_direction = order.iter().map(|o| o.direction).all_equal();
+    let nullable = order
+        .iter()
+        .any(|o| o.nulls.is_some() || (!o.not_null && o.nulls_last_in_scan(binder.dialect)))
+        || values.iter().any(Value::is_null);
+
+    // simple case: a row value comparison can use a composite index directly
+    let row_values = order.len() == 1 || binder.dialect.supports_row_values();
+    if same_direction && !nullable && row_values {
+        let op = match order[0].direction {
+            Direction::Asc => ">",
+            Direction::Desc => "<",
+        };
+        let values = values.iter().map(|v| binder.bind(v)).collect::<Vec<_>>();
+        let columns = order
+            .iter()
+            .map(|o| o.column.to_sql(binder.dialect))
+            .collect::<Vec<_>>();
+        return if order.len() == 1 {
+            format!("{} {op} {}", columns[0], values[0])
+        } else {
+            format!("({}) {op} ({})", columns.join(", "), values.join(", "))
+        };
+    }
+
+    // expanded form: (a after x) OR (a = x AND b after y) OR ...
+    // values are bound in the order they appear in the SQL text
+    let mut terms = Vec::new();
+    for (i, (o, v)) in order.iter().zip(values).enumerate() {
+        if !o.has_after(v, binder.dialect) {
+            continue;
+        }
+        let mut conds = order[..i]
+            .iter()
+            .zip(values)
+            .map(|(o, v)| o.equals(v, binder))
+            .collect::<Vec<_>>();
+        conds.push(o.after(v, binder));
+        terms.push(if conds.len() > 1 {
+            format!("({})", conds.join(" AND "))
+        } else {
+            conds.remove(0)
+        });
+    }
+
+    match terms.len() {
+        0 => "1 = 0".to_owned(),
+        1 => terms.remove(0),
+        _ => format!("({})", terms.join(" OR ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GenericDialect, MsSqlDialect, MySqlDialect, PostgresDialect};
+
+    fn seek(order: &[OrderBy], values: &[Value]) -> String {
+        seek_predicate(
+            order,
+            values,
+            &mut Binder::inline(&GenericDialect::default()),
+        )
+    }
+
+    #[test]
+    fn order_by_should_parse_and_render() -> Result<()> {
+        for s in [
+            "id",
+            "id DESC",
+            "name NULLS FIRST",
+            "created_at DESC NULLS LAST",
+        ] {
+            assert_eq!(s.parse::<OrderBy>()?.to_string(), s);
+        }
+        assert_eq!("id asc".parse::<OrderBy>()?, OrderBy::asc("id"));
+        assert!("id DESC NULLS".parse::<OrderBy>().is_err());
+        assert!("id sideways".parse::<OrderBy>().is_err());
+        assert!("".parse::<OrderBy>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn seek_predicate_should_use_row_value_for_uniform_order() {
+        let order = [
+            OrderBy::desc("created_at").not_null(),
+            OrderBy::desc("id").not_null(),
+        ];
+        let values = ["2023-02-06".into(), 42.into()];
+        assert_eq!(
+            seek(&order, &values),
+            "(created_at, id) < ('2023-02-06', 42)"
+        );
+
+        // SQL Server has no row value comparison
+        let mut binder = Binder::inline(&MsSqlDialect);
+        assert_eq!(
+            seek_predicate(&order, &values, &mut binder),
+            "([created_at] < '2023-02-06' OR ([created_at] = '2023-02-06' AND [id] < 42))"
+        );
+    }
+
+    #[test]
+    fn seek_predicate_should_expand_mixed_directions() {
+        let order = [OrderBy::desc("score"), OrderBy::asc("id").not_null()];
+        let values = [90.into(), 7.into()];
+        assert_eq!(
+            seek(&order, &values),
+            "(score < 90 OR (score = 90 AND id > 7))"
+        );
+
+        // paging backwards uses the reversed order, where NULL scores come last
+        let order = order.iter().map(OrderBy::reversed).collect::<Vec<_>>();
+        assert_eq!(
+            seek(&order, &values),
+            "((score > 90 OR score IS NULL) OR (score = 90 AND id < 7))"
+        );
+    }
+
+    #[test]
+    fn seek_predicate_should_handle_nulls() {
+        let order = [
+            OrderBy::asc("email").nulls_last(),
+            OrderBy::asc("id").not_null(),
+        ];
+        assert_eq!(
+            seek(&order, &["a@b.c".into(), 1.into()]),
+            "((email > 'a@b.c' OR email IS NULL) OR (email = 'a@b.c' AND id > 1))"
+        );
+        // nothing sorts after NULL when NULLs are last
+        assert_eq!(
+            seek(&order, &[Value::Null, 1.into()]),
+            "(email IS NULL AND id > 1)"
+        );
+
+        let order = [
+            OrderBy::asc("email").nulls_first(),
+            OrderBy::asc("id").not_null(),
+        ];
+        assert_eq!(
+            seek(&order, &[Value::Null, 1.into()]),
+            "(email IS NOT NULL OR (email IS NULL AND id > 1))"
+        );
+    }
+
+    #[test]
+    fn seek_predicate_should_use_dialect_null_ordering() {
+        // NULLs are the largest values in PostgreSQL, so they come after any email
+        let order = [OrderBy::asc("email"), OrderBy::asc("id").not_null()];
+        let mut binder = Binder::inline(&PostgresDialect);
+        assert_eq!(
+            seek_predicate(&order, &["a@b.c".into(), 1.into()], &mut binder),
+            r#"(("email" > 'a@b.c' OR "email" IS NULL) OR ("email" = 'a@b.c' AND "id" > 1))"#
+        );
+
+        // NULLs are the smallest values in MySQL, so they come first when sorting ascending
+        let order = [OrderBy::asc("email"), OrderBy::asc("id")];
+        let mut binder = Binder::inline(&MySqlDialect);
+        assert_eq!(
+            seek_predicate(&order, &[Value::Null, 1.into()], &mut binder),
+            "(`email` IS NOT NULL OR (`email` IS NULL AND `id` > 1))"
+        );
+    }
+}
@@ -1,78 +1,438 @@
-use std::{cmp::max, collections::VecDeque};
+use crate::{error::*, Cursor, Value};
+use serde::{de::DeserializeOwned, Deserialize, Serialize, Serializer};
+use snafu::{ensure, OptionExt};
+use std::{collections::VecDeque, fmt};
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Offset pagination. The cursor is serialized as an opaque string (see `OpaqueCursor`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PageInfo {
+    #[serde(with = "cursor", default)]
     pub cursor: Option<u64>,
     pub page_size: u64,
+    /// deepest offset a page can start at. There is no next page beyond it. It is set by the
+    /// server, so it is never serialized
+    #[serde(skip)]
+    pub max_offset: Option<u64>,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct Pager {
-    pub prev: Option<u64>,
-    pub next: Option<u64>,
-    pub total: Option<u64>,
+/// Page-number pagination, e.g. for "Page 3 of 47" with numbered links. The cursors of its
+/// pager are page numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageNumber {
+    /// current page, starting from 1
+    pub page: u64,
+    pub page_size: u64,
 }
+
+/// The pager of a page. It is serialized as:
+///
+/// ```json
+/// {
+///   "prev": null,
+///   "next": "gAAACg",
+///   "first": null,
+///   "last": null,
+///   "total": { "count": 42, "kind": "exact" },
+///   "page_size": 10,
+///   "page": 1
+/// }
+/// ```
+///
+/// All the fields are always present, missing cursors and totals are `null`. Cursors are opaque
+/// strings (see `OpaqueCursor`), except the page numbers of a `PageNumber` pager, which are
+/// plain integers. The kind of a total is `exact`, `at_least` or `estimate`. Wrap the pager in
+/// `CamelCase` for camelCase names and kinds, e.g. `pageSize` and `atLeast`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "C: PagerCursor", deserialize = "C: PagerCursor"))]
+pub struct Pager<C = u64> {
+    #[serde(with = "cursor", default)]
+    pub prev: Option<C>,
+    #[serde(with = "cursor", default)]
+    pub next: Option<C>,
+    /// cursor of the first page, if not on it
+    #[serde(with = "cursor", default)]
+    pub first: Option<C>,
+    /// cursor of the last page, if not on it. When paging by offset it needs an exact total (see
+    /// `PageInfo::pager_with_total` and `SqlQuery::pager_with_total`)
+    #[serde(with = "cursor", default)]
+    pub last: Option<C>,
+    /// total number of items, see `with_total`
+    #[serde(default)]
+    pub total: Option<Total>,
+    /// number of items per page
+    pub page_size: u64,
+    /// current page number, starting from 1. Only known when paging by offset
+    #[serde(default)]
+    pub page: Option<u64>,
+}
+
+/// Total number of items, which may be inexact for large tables (see `TotalPolicy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Total {
+    pub count: u64,
+    pub kind: TotalKind,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalKind {
+    /// the exact number of items
+    #[default]
+    Exact,
+    /// there are more items than the count
+    AtLeast,
+    /// estimated from database statistics
+    Estimate,
+}
+
+/// A page number of a `PageNumber` pager, starting from 1. It is serialized as a plain integer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PageNo(pub u64);
+
+/// Serialize pagers, pages and totals with camelCase names and kinds, e.g. `pageSize` and
+/// `atLeast`, for JavaScript clients: `serde_json::to_string(&CamelCase(&pager))`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CamelCase<T>(pub T);
+
 pub trait Paginator: Sized {
-    fn get_pager<T: Container>(&self, data: &mut T) -> Pager;
-    fn next_page(&self, pager: &Pager) -> Option<Self>;
-    fn prev_page(&self, pager: &Pager) -> Option<Self>;
+    /// cursor of the pages of the pager, e.g. an offset
+    type Cursor;
+
+    /// get the pager for the fetched data (page_size + 1 items), the extra item is removed. It
+    /// fails if the next page can't be represented
+    fn get_pager<T: Container>(&self, data: &mut T) -> Result<Pager<Self::Cursor>>;
+    fn next_page(&self, pager: &Pager<Self::Cursor>) -> Option<Self>;
+    fn prev_page(&self, pager: &Pager<Self::Cursor>) -> Option<Self>;
+    fn first_page(&self) -> Self;
+    /// the last page, if it is known from the pager or from its exact total
+    fn last_page(&self, pager: &Pager<Self::Cursor>) -> Option<Self>;
 }
 
 pub trait Container {
+    type Item;
+
     fn pop(&mut self);
+    fn pop_front(&mut self);
+    fn reverse(&mut self);
     fn len(&self) -> usize;
+    fn first(&self) -> Option<&Self::Item>;
+    fn last(&self) -> Option<&Self::Item>;
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
 }
 
+/// Cursors of a pager, serialized as opaque strings. Offsets are encoded as offset cursors. They
+/// are neither signed nor bound to a query, map the pager with `SqlQuery::encode_cursor` to a
+/// pager of strings for that
+pub trait OpaqueCursor: Sized {
+    fn encode_opaque(&self) -> String;
+    fn decode_opaque(s: &str) -> Result<Self>;
+}
+
+/// How the cursors of a pager are serialized: opaque strings or page numbers
+pub trait PagerCursor: Sized {
+    type Repr: Serialize + DeserializeOwned;
+
+    fn to_repr(&self) -> Self::Repr;
+    fn from_repr(repr: Self::Repr) -> Result<Self>;
+}
+
+/// Rows that can be paginated by keyset need to expose the values of their sort key columns
+pub trait SortKey {
+    /// value of the given sort key column for this row
+    fn sort_key(&self, column: &str) -> Value;
+}
+
+impl<C> Pager<C> {
+    pub fn map<U>(self, f: impl Fn(C) -> U) -> Pager<U> {
+        Pager {
+            prev: self.prev.map(&f),
+            next: self.next.map(&f),
+            first: self.first.map(&f),
+            last: self.last.map(&f),
+            total: self.total,
+            page_size: self.page_size,
+            page: self.page,
+        }
+    }
+
+    /// attach the total number of items, e.g. the result of `SqlQuery::to_count_sql`. A plain
+    /// number is an exact total
+    pub fn with_total(self, total: impl Into<Total>) -> Self {
+        Self {
+            total: Some(total.into()),
+            ..self
+        }
+    }
+
+    /// page numbers around the current page to render as links, at most `width` of them. The
+    /// last page is the total number of pages if known, otherwise the next page if there is one
+    pub fn page_window(&self, width: u64) -> Vec<u64> {
+        let Some(page) = self.page.filter(|_| width > 0) else {
+            return Vec::new();
+        };
+        let last = match self.total_pages() {
+            Some(pages) => pages.max(page),
+            None => page.saturating_add(self.next.is_some() as u64),
+        };
+        let end = page.saturating_add(width / 2).max(width).min(last);
+        let start = end.saturating_sub(width.saturating_sub(1)).max(1);
+        (start..=end).collect()
+    }
+
+    /// total number of pages, if the total is known. It is a lower bound or an estimate if the
+    /// total is
+    pub fn total_pages(&self) -> Option<u64> {
+        match self.page_size {
+            0 => None,
+            size => self.total.map(|total| total.count.div_ceil(size)),
+        }
+    }
+}
+
+impl Total {
+    pub fn exact(count: u64) -> Self {
+        Self {
+            count,
+            kind: TotalKind::Exact,
+        }
+    }
+
+    pub fn at_least(count: u64) -> Self {
+        Self {
+            count,
+            kind: TotalKind::AtLeast,
+        }
+    }
+
+    pub fn estimate(count: u64) -> Self {
+        Self {
+            count,
+            kind: TotalKind::Estimate,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.kind == TotalKind::Exact
+    }
+}
+
+impl From<u64> for Total {
+    fn from(count: u64) -> Self {
+        Self::exact(count)
+    }
+}
+
+/// display the total for humans, e.g. `1000`, `1000+` or `~1000`
+impl fmt::Display for Total {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TotalKind::Exact => write!(f, "{}", self.count),
+            TotalKind::AtLeast => write!(f, "{}+", self.count),
+            TotalKind::Estimate => write!(f, "~{}", self.count),
+        }
+    }
+}
+
 impl<T> Container for VecDeque<T> {
+    type Item = T;
+
     fn pop(&mut self) {
         self.pop_back();
     }
 
+    fn pop_front(&mut self) {
+        self.pop_front();
+    }
+
+    fn reverse(&mut self) {
+        self.make_contiguous().reverse();
+    }
+
     fn len(&self) -> usize {
         self.len()
     }
+
+    fn first(&self) -> Option<&T> {
+        self.front()
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.back()
+    }
 }
 
 impl<T> Container for Vec<T> {
+    type Item = T;
+
     fn pop(&mut self) {
         self.pop();
     }
 
+    fn pop_front(&mut self) {
+        if !self.is_empty() {
+            self.remove(0);
+        }
+    }
+
+    fn reverse(&mut self) {
+        self.as_mut_slice().reverse();
+    }
+
     fn len(&self) -> usize {
         self.len()
     }
+
+    fn first(&self) -> Option<&T> {
+        self.as_slice().first()
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
 }
 
-impl Paginator for PageInfo {
-    fn get_pager<T: Container>(&self, data: &mut T) -> Pager {
-        let prev = match self.cursor {
-            Some(v) if v > 0 => Some(max(0, v - self.page_size)),
-            _ => None,
+impl PageNumber {
+    /// page numbers start from 1, page 0 is treated as the first page
+    pub fn new(page: u64, page_size: u64) -> Self {
+        Self {
+            page: page.max(1),
+            page_size,
+        }
+    }
+
+    /// number of items to skip
+    pub fn offset(&self) -> Result<u64> {
+        let skipped = self.page.saturating_sub(1);
+        skipped
+            .checked_mul(self.page_size)
+            .context(OffsetOverflowSnafu {
+                offset: skipped.saturating_mul(self.page_size),
+                page_size: self.page_size,
+            })
+    }
+
+    pub fn first(&self) -> Self {
+        self.jump_to(1)
+    }
+
+    /// the last page, if the total is exact
+    pub fn last(&self, pager: &Pager<PageNo>) -> Option<Self> {
+        let total = pager.total.filter(Total::is_exact)?;
+        match pager.page_size {
+            0 => None,
+            size => Some(self.jump_to(total.count.div_ceil(size))),
+        }
+    }
+
+    /// attach the total to the pager of this page. An exact total also gives the last page,
+    /// unless it is the current one
+    pub fn pager_with_total(&self, pager: Pager<PageNo>, total: impl Into<Total>) -> Pager<PageNo> {
+        let pager = pager.with_total(total);
+        let last = pager.last.or_else(|| {
+            self.last(&pager)
+                .map(|last| PageNo(last.page))
+                .filter(|&PageNo(last)| last != self.page)
+        });
+        Pager { last, ..pager }
+    }
+
+    pub fn jump_to(&self, page: u64) -> Self {
+        Self::new(page, self.page_size)
+    }
+}
+
+impl Paginator for PageNumber {
+    type Cursor = PageNo;
+
+    fn get_pager<T: Container>(&self, data: &mut T) -> Result<Pager<PageNo>> {
+        let offset = self.offset()?;
+        let has_next = data.len() as u64 > self.page_size;
+        let next = if has_next {
+            data.pop();
+            let next = self.page.checked_add(1).context(OffsetOverflowSnafu {
+                offset,
+                page_size: self.page_size,
+            })?;
+            Some(PageNo(next))
+        } else {
+            None
         };
 
+        Ok(Pager {
+            prev: (self.page > 1).then(|| PageNo(self.page - 1)),
+            next,
+            first: (self.page > 1).then_some(PageNo(1)),
+            last: None,
+            total: None,
+            page_size: self.page_size,
+            page: Some(self.page),
+        })
+    }
+
+    fn next_page(&self, pager: &Pager<PageNo>) -> Option<Self> {
+        pager.next.map(|PageNo(page)| self.jump_to(page))
+    }
+
+    fn prev_page(&self, pager: &Pager<PageNo>) -> Option<Self> {
+        pager.prev.map(|PageNo(page)| self.jump_to(page))
+    }
+
+    fn first_page(&self) -> Self {
+        self.first()
+    }
+
+    fn last_page(&self, pager: &Pager<PageNo>) -> Option<Self> {
+        match pager.last {
+            Some(PageNo(page)) => Some(self.jump_to(page)),
+            None => self.last(pager),
+        }
+    }
+}
+
+impl Paginator for PageInfo {
+    type Cursor = u64;
+
+    fn get_pager<T: Container>(&self, data: &mut T) -> Result<Pager> {
+        let offset = self.cursor.unwrap_or(0);
+        // a cursor not aligned to the page size can't go before the first item
+        let prev = (offset > 0).then(|| offset.saturating_sub(self.page_size));
+
         let has_next = data.len() as u64 > self.page_size;
         let next = if has_next {
             data.pop();
-            Some(self.cursor.unwrap_or(0) + self.page_size)
+            let next = offset
+                .checked_add(self.page_size)
+                .context(OffsetOverflowSnafu {
+                    offset,
+                    page_size: self.page_size,
+                })?;
+            Some(next).filter(|next| self.max_offset.is_none_or(|max| *next <= max))
         } else {
             None
         };
 
-        Pager {
+        Ok(Pager {
             prev,
             next,
+            first: (offset > 0).then_some(0),
+            last: None,
             total: None,
-        }
+            page_size: self.page_size,
+            page: match self.page_size {
+                0 => None,
+                size => Some((offset / size).saturating_add(1)),
+            },
+        })
     }
 
     fn next_page(&self, pager: &Pager) -> Option<Self> {
         if pager.next.is_some() {
             Some(PageInfo {
                 cursor: pager.next,
-                page_size: self.page_size,
+                ..self.clone()
             })
         } else {
             None
@@ -83,19 +443,249 @@ impl Paginator for PageInfo {
         if pager.prev.is_some() {
             Some(PageInfo {
                 cursor: pager.prev,
-                page_size: self.page_size,
+                ..self.clone()
             })
         } else {
             None
         }
     }
+
+    fn first_page(&self) -> Self {
+        PageInfo {
+            cursor: None,
+            ..self.clone()
+        }
+    }
+
+    fn last_page(&self, pager: &Pager) -> Option<Self> {
+        let last = pager.last.or_else(|| self.last_offset(pager.total));
+        last.map(|offset| PageInfo {
+            cursor: Some(offset),
+            ..self.clone()
+        })
+    }
+}
+
+impl PageInfo {
+    /// attach the total to the pager of this page. An exact total also gives the last page,
+    /// unless it is the current one
+    pub fn pager_with_total(&self, pager: Pager, total: impl Into<Total>) -> Pager {
+        let pager = pager.with_total(total);
+        let last = pager.last.or_else(|| self.last_link(pager.total));
+        Pager { last, ..pager }
+    }
+
+    /// offset of the last page to link to, unless it is the current one
+    pub(crate) fn last_link(&self, total: Option<Total>) -> Option<u64> {
+        self.last_offset(total)
+            .filter(|&last| last != self.cursor.unwrap_or(0))
+    }
+
+    /// offset of the last page. The total must be exact and the last page within the max offset
+    pub(crate) fn last_offset(&self, total: Option<Total>) -> Option<u64> {
+        let total = total.filter(Total::is_exact)?;
+        let pages = total.count.div_ceil(self.page_size.max(1));
+        let offset = pages.saturating_sub(1).checked_mul(self.page_size)?;
+        self.max_offset
+            .is_none_or(|max| offset <= max)
+            .then_some(offset)
+    }
+}
+
+impl OpaqueCursor for u64 {
+    fn encode_opaque(&self) -> String {
+        Cursor::Offset(*self).encode()
+    }
+
+    fn decode_opaque(s: &str) -> Result<Self> {
+        Cursor::decode(s)?
+            .offset()
+            .context(InvalidCursorSnafu { cursor: s })
+    }
+}
+
+impl OpaqueCursor for Cursor {
+    fn encode_opaque(&self) -> String {
+        self.encode()
+    }
+
+    fn decode_opaque(s: &str) -> Result<Self> {
+        Cursor::decode(s)
+    }
+}
+
+/// already encoded cursors, e.g. with `SqlQuery::encode_cursor`
+impl OpaqueCursor for String {
+    fn encode_opaque(&self) -> String {
+        self.clone()
+    }
+
+    fn decode_opaque(s: &str) -> Result<Self> {
+        Ok(s.to_owned())
+    }
+}
+
+impl<C: OpaqueCursor> PagerCursor for C {
+    type Repr = String;
+
+    fn to_repr(&self) -> String {
+        self.encode_opaque()
+    }
+
+    fn from_repr(repr: String) -> Result<Self> {
+        Self::decode_opaque(&repr)
+    }
+}
+
+impl PagerCursor for PageNo {
+    type Repr = u64;
+
+    fn to_repr(&self) -> u64 {
+        self.0
+    }
+
+    fn from_repr(repr: u64) -> Result<Self> {
+        ensure!(
+            repr >= 1,
+            InvalidCursorSnafu {
+                cursor: repr.to_string()
+            }
+        );
+        Ok(Self(repr))
+    }
+}
+
+impl fmt::Display for PageNo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<C: PagerCursor> Serialize for CamelCase<&Pager<C>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Repr<'a, R> {
+            prev: Option<R>,
+            next: Option<R>,
+            first: Option<R>,
+            last: Option<R>,
+            total: Option<CamelCase<&'a Total>>,
+            page_size: u64,
+            page: Option<u64>,
+        }
+
+        let pager = self.0;
+        Repr {
+            prev: pager.prev.as_ref().map(C::to_repr),
+            next: pager.next.as_ref().map(C::to_repr),
+            first: pager.first.as_ref().map(C::to_repr),
+            last: pager.last.as_ref().map(C::to_repr),
+            total: pager.total.as_ref().map(CamelCase),
+            page_size: pager.page_size,
+            page: pager.page,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for CamelCase<&PageInfo> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Repr {
+            cursor: Option<String>,
+            page_size: u64,
+        }
+
+        Repr {
+            cursor: self.0.cursor.as_ref().map(u64::to_repr),
+            page_size: self.0.page_size,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for CamelCase<&PageNumber> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Repr {
+            page: u64,
+            page_size: u64,
+        }
+
+        Repr {
+            page: self.0.page,
+            page_size: self.0.page_size,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for CamelCase<&Total> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            count: u64,
+            kind: CamelCase<&'a TotalKind>,
+        }
+
+        Repr {
+            count: self.0.count,
+            kind: CamelCase(&self.0.kind),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for CamelCase<&TotalKind> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (index, name) = match self.0 {
+            TotalKind::Exact => (0, "exact"),
+            TotalKind::AtLeast => (1, "atLeast"),
+            TotalKind::Estimate => (2, "estimate"),
+        };
+        serializer.serialize_unit_variant("TotalKind", index, name)
+    }
+}
+
+/// serialize optional cursors of pagers, see `PagerCursor`
+mod cursor {
+    use super::PagerCursor;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<C, S>(cursor: &Option<C>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        C: PagerCursor,
+        S: Serializer,
+    {
+        cursor.as_ref().map(C::to_repr).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, C, D>(deserializer: D) -> Result<Option<C>, D::Error>
+    where
+        C: PagerCursor,
+        D: Deserializer<'de>,
+    {
+        Option::<C::Repr>::deserialize(deserializer)?
+            .map(|repr| C::from_repr(repr).map_err(D::Error::custom))
+            .transpose()
+    }
 }
 
 #[cfg(test)]
 pub mod pager_test_utils {
+    use crate::{SortKey, Value};
     use std::collections::VecDeque;
     pub struct TestId(u64);
 
+    impl SortKey for TestId {
+        fn sort_key(&self, _column: &str) -> Value {
+            Value::Int(self.0 as i64)
+        }
+    }
+
     pub fn generate_test_ids(start: u64, end: u64) -> VecDeque<TestId> {
         (start..=end).map(TestId).collect()
     }
@@ -104,6 +694,7 @@ pub mod pager_test_utils {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn paginator_should_work() {
@@ -111,11 +702,12 @@ mod tests {
         let page = PageInfo {
             cursor: None,
             page_size: 10,
+            ..Default::default()
         };
 
         // assume we got 11 items from db
         let mut items = pager_test_utils::generate_test_ids(1, 11);
-        let pager = page.get_pager(&mut items);
+        let pager = page.get_pager(&mut items).unwrap();
         assert!(pager.prev.is_none());
         assert_eq!(pager.next, Some(10));
 
@@ -127,7 +719,7 @@ mod tests {
         // second page
         let page = page.next_page(&pager).unwrap();
         let mut items = pager_test_utils::generate_test_ids(11, 21);
-        let pager = page.get_pager(&mut items);
+        let pager = page.get_pager(&mut items).unwrap();
         assert_eq!(pager.prev, Some(0));
         assert_eq!(pager.next, Some(20));
 
@@ -139,7 +731,7 @@ mod tests {
         // third page
         let page = page.next_page(&pager).unwrap();
         let mut items = pager_test_utils::generate_test_ids(21, 25);
-        let pager = page.get_pager(&mut items);
+        let pager = page.get_pager(&mut items).unwrap();
         assert_eq!(pager.prev, Some(10));
         assert!(pager.next.is_none());
 
@@ -148,4 +740,347 @@ mod tests {
             assert_eq!(prev_page.unwrap().cursor, Some(10));
         }
     }
+
+    #[test]
+    fn pager_should_derive_page_numbers() {
+        let page = PageInfo {
+            cursor: Some(20),
+            page_size: 10,
+            ..Default::default()
+        };
+        let mut items = pager_test_utils::generate_test_ids(21, 31);
+        let pager = page.get_pager(&mut items).unwrap();
+        assert_eq!(pager.page, Some(3));
+        assert_eq!(pager.total_pages(), None);
+
+        let pager = pager.with_total(95);
+        assert_eq!(pager.total, Some(Total::exact(95)));
+        assert_eq!(pager.total_pages(), Some(10));
+        assert_eq!(pager.clone().with_total(0).total_pages(), Some(0));
+
+        let pager = pager.with_total(Total::at_least(1000));
+        assert_eq!(pager.total_pages(), Some(100));
+        assert_eq!(pager.total.unwrap().to_string(), "1000+");
+        assert_eq!(Total::estimate(1000).to_string(), "~1000");
+        assert_eq!(Total::exact(1000).to_string(), "1000");
+    }
+
+    #[test]
+    fn page_number_should_work() {
+        let page = PageNumber::new(3, 10);
+        assert_eq!(page.offset().unwrap(), 20);
+
+        let mut items = pager_test_utils::generate_test_ids(21, 31);
+        let pager = page.get_pager(&mut items).unwrap().with_total(465);
+        assert_eq!(items.len(), 10);
+        assert_eq!(
+            (pager.prev, pager.page, pager.next),
+            (Some(PageNo(2)), Some(3), Some(PageNo(4)))
+        );
+        assert_eq!(pager.total_pages(), Some(47));
+        assert_eq!(page.next_page(&pager), Some(PageNumber::new(4, 10)));
+        assert_eq!(page.prev_page(&pager), Some(PageNumber::new(2, 10)));
+        assert_eq!(page.first(), PageNumber::new(1, 10));
+        assert_eq!(page.last(&pager), Some(PageNumber::new(47, 10)));
+        assert_eq!(page.jump_to(0).page, 1);
+
+        // only an exact total gives the last page
+        let mut items = pager_test_utils::generate_test_ids(21, 31);
+        let estimated = page.get_pager(&mut items).unwrap();
+        let estimated = page.pager_with_total(estimated, Total::estimate(1000));
+        assert_eq!(estimated.last, None);
+        assert_eq!(page.last(&estimated), None);
+        assert_eq!(page.last_page(&estimated), None);
+        let exact = page.get_pager(&mut items).unwrap();
+        let exact = page.pager_with_total(exact, 465);
+        assert_eq!(exact.last, Some(PageNo(47)));
+        assert_eq!(page.last_page(&exact), Some(PageNumber::new(47, 10)));
+        let last = page.jump_to(47).get_pager(&mut items).unwrap();
+        assert_eq!(page.jump_to(47).pager_with_total(last, 465).last, None);
+
+        assert_eq!(pager.page_window(5), vec![1, 2, 3, 4, 5]);
+        let pager = page
+            .jump_to(20)
+            .get_pager(&mut items)
+            .unwrap()
+            .with_total(465);
+        assert_eq!(pager.page_window(5), vec![18, 19, 20, 21, 22]);
+        let pager = page
+            .jump_to(46)
+            .get_pager(&mut items)
+            .unwrap()
+            .with_total(465);
+        assert_eq!(pager.page_window(5), vec![43, 44, 45, 46, 47]);
+
+        // without a total, the window ends at the next page
+        let mut items = pager_test_utils::generate_test_ids(1, 11);
+        let pager = page.jump_to(7).get_pager(&mut items).unwrap();
+        assert_eq!(pager.page_window(4), vec![5, 6, 7, 8]);
+        assert_eq!(page.last(&pager), None);
+    }
+
+    #[test]
+    fn paginator_should_stop_at_max_offset() {
+        let page = PageInfo {
+            cursor: Some(80),
+            page_size: 10,
+            max_offset: Some(90),
+        };
+        let mut items = pager_test_utils::generate_test_ids(81, 91);
+        let pager = page.get_pager(&mut items).unwrap();
+        assert_eq!(pager.next, Some(90));
+
+        let page = page.next_page(&pager).unwrap();
+        assert_eq!(page.max_offset, Some(90));
+        let mut items = pager_test_utils::generate_test_ids(91, 101);
+        let pager = page.get_pager(&mut items).unwrap();
+        assert_eq!(items.len(), 10);
+        assert_eq!(pager.prev, Some(80));
+        assert_eq!(pager.next, None);
+    }
+
+    #[test]
+    fn paginator_should_go_to_first_and_last_page() {
+        let page = PageInfo {
+            cursor: Some(20),
+            page_size: 10,
+            ..Default::default()
+        };
+        let mut items = pager_test_utils::generate_test_ids(21, 31);
+        let pager = page.get_pager(&mut items).unwrap();
+        assert_eq!((pager.first, pager.last), (Some(0), None));
+        assert_eq!(page.first_page().cursor, None);
+        assert_eq!(page.last_page(&pager), None);
+
+        let last = page.last_page(&pager.clone().with_total(50)).unwrap();
+        assert_eq!(last.cursor, Some(40));
+        assert_eq!(
+            page.last_page(&pager.clone().with_total(0)).unwrap().cursor,
+            Some(0)
+        );
+        assert_eq!(
+            page.last_page(&pager.clone().with_total(Total::at_least(50))),
+            None
+        );
+
+        // the pager carries the last page once the exact total is known
+        assert_eq!(page.pager_with_total(pager.clone(), 50).last, Some(40));
+        assert_eq!(page.pager_with_total(pager.clone(), 30).last, None);
+        assert_eq!(
+            page.pager_with_total(pager.clone(), Total::estimate(50))
+                .last,
+            None
+        );
+
+        // beyond the max offset
+        let page = PageInfo {
+            max_offset: Some(30),
+            ..page
+        };
+        let pager = page
+            .get_pager(&mut pager_test_utils::generate_test_ids(21, 31))
+            .unwrap();
+        assert_eq!(page.last_page(&pager.with_total(50)), None);
+
+        let page = PageNumber::new(3, 10);
+        let pager = page
+            .get_pager(&mut pager_test_utils::generate_test_ids(21, 31))
+            .unwrap();
+        assert_eq!(pager.first, Some(PageNo(1)));
+        assert_eq!(page.first_page(), PageNumber::new(1, 10));
+        assert_eq!(
+            page.last_page(&pager.with_total(45)),
+            Some(PageNumber::new(5, 10))
+        );
+    }
+
+    #[test]
+    fn paginator_should_not_underflow_or_overflow() {
+        // a cursor not aligned to the page size
+        let page = PageInfo {
+            cursor: Some(5),
+            page_size: 10,
+            ..Default::default()
+        };
+        let mut items = pager_test_utils::generate_test_ids(6, 16);
+        let pager = page.get_pager(&mut items).unwrap();
+        assert_eq!(pager.prev, Some(0));
+        assert_eq!(pager.next, Some(15));
+
+        let page = PageInfo {
+            cursor: Some(u64::MAX - 5),
+            ..page
+        };
+        let mut items = pager_test_utils::generate_test_ids(1, 11);
+        let err = page.get_pager(&mut items).unwrap_err();
+        assert!(matches!(err, Error::OffsetOverflow { page_size: 10, .. }));
+
+        let page = PageNumber::new(u64::MAX, 10);
+        let mut items = pager_test_utils::generate_test_ids(1, 11);
+        assert!(page.get_pager(&mut items).is_err());
+    }
+
+    #[test]
+    fn pager_should_serialize_to_stable_json() -> anyhow::Result<()> {
+        let page = PageInfo {
+            cursor: Some(10),
+            page_size: 10,
+            max_offset: Some(100),
+        };
+        let mut items = pager_test_utils::generate_test_ids(11, 21);
+        let pager = page
+            .get_pager(&mut items)?
+            .with_total(Total::at_least(1000));
+
+        let json = serde_json::to_value(&pager)?;
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "prev": Cursor::Offset(0).encode(),
+                "next": Cursor::Offset(20).encode(),
+                "first": Cursor::Offset(0).encode(),
+                "last": null,
+                "total": { "count": 1000, "kind": "at_least" },
+                "page_size": 10,
+                "page": 2,
+            })
+        );
+        assert_eq!(serde_json::from_value::<Pager>(json)?, pager);
+
+        // opt in to camelCase
+        assert_eq!(
+            serde_json::to_value(CamelCase(&pager))?,
+            serde_json::json!({
+                "prev": Cursor::Offset(0).encode(),
+                "next": Cursor::Offset(20).encode(),
+                "first": Cursor::Offset(0).encode(),
+                "last": null,
+                "total": { "count": 1000, "kind": "atLeast" },
+                "pageSize": 10,
+                "page": 2,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(CamelCase(&page))?,
+            serde_json::json!({ "cursor": Cursor::Offset(10).encode(), "pageSize": 10 })
+        );
+
+        // keyset cursors, and missing fields
+        let pager = Pager {
+            prev: None,
+            next: Some(Cursor::After(vec!["a".into()])),
+            first: None,
+            last: Some(Cursor::Before(vec![])),
+            total: None,
+            page_size: 10,
+            page: None,
+        };
+        let json = serde_json::to_string(&pager)?;
+        assert_eq!(serde_json::from_str::<Pager<Cursor>>(&json)?, pager);
+        let json = r#"{"page_size": 10}"#;
+        assert_eq!(serde_json::from_str::<Pager<Cursor>>(json)?.page_size, 10);
+        let json = r#"{"next": "abc!", "page_size": 10}"#;
+        assert!(serde_json::from_str::<Pager>(json).is_err());
+
+        // page numbers are plain integers
+        let numbered = PageNumber::new(2, 10);
+        let pager = numbered
+            .get_pager(&mut pager_test_utils::generate_test_ids(11, 21))?
+            .with_total(Total::estimate(45));
+        let json = serde_json::to_value(&pager)?;
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "prev": 1,
+                "next": 3,
+                "first": 1,
+                "last": null,
+                "total": { "count": 45, "kind": "estimate" },
+                "page_size": 10,
+                "page": 2,
+            })
+        );
+        assert_eq!(serde_json::from_value::<Pager<PageNo>>(json)?, pager);
+        assert!(serde_json::from_str::<Pager<PageNo>>(r#"{"next": 0, "page_size": 10}"#).is_err());
+        assert_eq!(
+            serde_json::to_value(CamelCase(&numbered))?,
+            serde_json::json!({ "page": 2, "pageSize": 10 })
+        );
+
+        // the max offset is set by the server
+        let json = serde_json::to_value(&page)?;
+        assert_eq!(
+            json,
+            serde_json::json!({ "cursor": Cursor::Offset(10).encode(), "page_size": 10 })
+        );
+        let page = serde_json::from_value::<PageInfo>(json)?;
+        assert_eq!((page.cursor, page.max_offset), (Some(10), None));
+        Ok(())
+    }
+
+    fn cursors() -> impl Strategy<Value = Option<u64>> {
+        proptest::option::of(prop_oneof![any::<u64>(), (u64::MAX - 128)..=u64::MAX])
+    }
+
+    fn page_sizes() -> impl Strategy<Value = u64> {
+        prop_oneof![1u64..64, any::<u64>()]
+    }
+
+    proptest! {
+        #[test]
+        fn page_info_arithmetic_should_be_checked(
+            cursor in cursors(),
+            page_size in page_sizes(),
+            len in 0u64..=64,
+            max_offset in proptest::option::of(any::<u64>()),
+        ) {
+            let page = PageInfo { cursor, page_size, max_offset };
+            let mut items = pager_test_utils::generate_test_ids(1, len);
+            let offset = cursor.unwrap_or(0);
+            let has_next = len > page_size;
+
+            match page.get_pager(&mut items) {
+                Ok(pager) => {
+                    prop_assert_eq!(items.len() as u64, len - has_next as u64);
+                    prop_assert_eq!(pager.prev, (offset > 0).then(|| offset.saturating_sub(page_size)));
+                    prop_assert!(pager.prev.is_none_or(|prev| prev < offset));
+                    match pager.next {
+                        Some(next) => {
+                            prop_assert!(has_next);
+                            prop_assert_eq!(next - offset, page_size);
+                            prop_assert!(max_offset.is_none_or(|max| next <= max));
+                        }
+                        None => prop_assert!(!has_next || max_offset.is_some()),
+                    }
+                    prop_assert!(pager.page.is_some_and(|page| page >= 1));
+                }
+                Err(err) => {
+                    prop_assert!(has_next && offset.checked_add(page_size).is_none());
+                    let is_overflow = matches!(err, Error::OffsetOverflow { .. });
+                    prop_assert!(is_overflow);
+                }
+            }
+        }
+
+        #[test]
+        fn page_number_arithmetic_should_be_checked(
+            page in any::<u64>(),
+            page_size in page_sizes(),
+            len in 0u64..=64,
+        ) {
+            let page = PageNumber::new(page, page_size);
+            let mut items = pager_test_utils::generate_test_ids(1, len);
+            let offset = (page.page - 1).checked_mul(page_size);
+            prop_assert_eq!(page.offset().ok(), offset);
+
+            if let Ok(pager) = page.get_pager(&mut items) {
+                prop_assert!(pager.prev.is_none_or(|PageNo(prev)| prev + 1 == page.page));
+                prop_assert!(pager.next.is_none_or(|PageNo(next)| next == page.page + 1));
+                prop_assert!(pager.page_window(5).iter().all(|p| *p >= 1));
+            } else {
+                // the offset of the page or the next page number overflows
+                prop_assert!(offset.is_none() || page.page == u64::MAX);
+            }
+        }
+    }
 }
@@ -0,0 +1,390 @@
+use crate::{
+    error::*, CursorCodec, Filter, Ident, OrderBy, PageMode, PagePolicy, SqlQuery, SqlQueryBuilder,
+    Value,
+};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt};
+use std::borrow::Cow;
+
+/// Type of a filterable column, used to parse filter values from the query string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+/// A resource exposed over HTTP, with the columns clients are allowed to sort and filter by.
+///
+/// It parses query strings like `?page_size=20&cursor=...&sort=-created_at,id&filter[status]=active`
+/// or `filter[age][gte]=18` into a validated `SqlQuery`. Supported filter operators are
+/// `eq` (the default), `ne`, `lt`, `lte`, `gt`, `gte`, `like`, `in` (comma separated values)
+/// and `null` (`true` or `false`). Other query parameters are ignored. The columns of the default
+/// order missing from `sort` are appended to it, to keep a unique tiebreaker for keyset pagination
+#[derive(Debug, Clone, Default, PartialEq, Eq, Builder)]
+#[builder(build_fn(name = "private_build"), setter(into, strip_option), default)]
+pub struct Resource<'a> {
+    /// source table or view
+    pub source: Ident<'a>,
+    /// fields to include in the result
+    pub projection: Vec<Ident<'a>>,
+    /// columns clients can sort by
+    pub sortable: Vec<Cow<'a, str>>,
+    /// columns clients can filter by, with the type of their values
+    pub filterable: Vec<(Cow<'a, str>, FieldType)>,
+    /// sort order used when the client doesn't give one
+    pub order: Vec<OrderBy<'a>>,
+    /// pagination mode
+    pub mode: PageMode,
+    /// page size limits
+    pub page_policy: PagePolicy,
+    /// encode and decode cursors, e.g. to sign them
+    pub codec: CursorCodec,
+}
+
+impl<'a> ResourceBuilder<'a> {
+    pub fn build(&self) -> Result<Resource<'a>, Error> {
+        let data = self
+            .private_build()
+            .expect("failed to build Resource struct");
+        data.validate()?;
+
+        Ok(data)
+    }
+}
+
+impl<'a> Resource<'a> {
+    /// parse a URL query string (with or without the leading `?`) into a `SqlQuery`
+    pub fn parse_query(&self, query: &str) -> Result<SqlQuery<'a>, Error> {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        self.parse_params(form_urlencoded::parse(query.as_bytes()))
+    }
+
+    /// build a `SqlQuery` from decoded query parameters, e.g. a `HashMap<String, String>`
+    pub fn parse_params<K, V>(
+        &self,
+        params: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<SqlQuery<'a>, Error>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut builder = SqlQueryBuilder::default();
+        builder
+            .source(self.source.clone())
+            .projection(self.projection.clone())
+            .order(self.order.clone())
+            .mode(self.mode)
+            .page_policy(self.page_policy)
+            .codec(self.codec.clone());
+
+        let mut filters = Vec::new();
+        for (key, value) in params {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            match key {
+                _ if value.is_empty() => {}
+                "page_size" => {
+                    let size = value
+                        .parse::<u64>()
+                        .ok()
+                        .context(InvalidParamSnafu { param: key, value })?;
+                    builder.page_size(size);
+                }
+                "cursor" => {
+                    builder.cursor(value.to_owned());
+                }
+                "sort" => {
+                    builder.order(self.parse_sort(value)?);
+                }
+                _ if key.starts_with("filter[") => {
+                    filters.push((key.to_owned(), self.parse_filter(key, value)?));
+                }
+                _ => {}
+            }
+        }
+
+        // keep the generated SQL stable regardless of the parameter order (e.g. from a HashMap)
+        filters.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(filter) = filters.into_iter().map(|(_, f)| f).reduce(Filter::and) {
+            builder.filter(filter);
+        }
+
+        builder.build()
+    }
+
+    /// make sure the page policy is consistent, and the source, the projection, the default
+    /// order and all the whitelisted columns are valid identifiers
+    pub fn validate(&self) -> Result<(), Error> {
+        self.page_policy.validate()?;
+        self.codec.validate()?;
+        ensure!(!self.source.is_empty(), InvalidSourceSnafu);
+        self.source.validate()?;
+        for ident in &self.projection {
+            ident.validate_projection()?;
+        }
+        for order in &self.order {
+            order.column.validate()?;
+        }
+        ensure!(
+            self.mode != PageMode::Keyset || !self.order.is_empty(),
+            InvalidKeysetSnafu
+        );
+        self.sortable
+            .iter()
+            .chain(self.filterable.iter().map(|(field, _)| field))
+            .try_for_each(|field| Ident::from(field.as_ref()).validate())
+    }
+
+    fn parse_sort(&self, value: &str) -> Result<Vec<OrderBy<'a>>, Error> {
+        let mut order = value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (name, desc) = match s.strip_prefix('-') {
+                    Some(name) => (name, true),
+                    None => (s.strip_prefix('+').unwrap_or(s), false),
+                };
+                let column = self
+                    .sortable
+                    .iter()
+                    .find(|c| c.as_ref() == name)
+                    .context(UnsortableFieldSnafu { field: name })?;
+                Ok(match desc {
+                    true => OrderBy::desc(column.clone()),
+                    false => OrderBy::asc(column.clone()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let missing = self
+            .order
+            .iter()
+            .filter(|o| !order.iter().any(|given| given.column == o.column))
+            .cloned()
+            .collect::<Vec<_>>();
+        order.extend(missing);
+        Ok(order)
+    }
+
+    fn parse_filter(&self, key: &str, value: &str) -> Result<Filter<'a>, Error> {
+        let (field, op) = parse_filter_key(key).context(InvalidParamSnafu { param: key, value })?;
+        let (column, ty) = self
+            .filterable
+            .iter()
+            .find(|(c, _)| c.as_ref() == field)
+            .context(UnfilterableFieldSnafu { field })?;
+
+        let parse = |v: &str| {
+            ty.parse(v).context(InvalidParamSnafu {
+                param: key,
+                value: v,
+            })
+        };
+        ensure!(
+            op != "like" || *ty == FieldType::String,
+            UnknownOperatorSnafu { op }
+        );
+
+        let column = column.clone();
+        Ok(match op {
+            "eq" => Filter::eq(column, parse(value)?),
+            "ne" => Filter::ne(column, parse(value)?),
+            "lt" => Filter::lt(column, parse(value)?),
+            "lte" => Filter::le(column, parse(value)?),
+            "gt" => Filter::gt(column, parse(value)?),
+            "gte" => Filter::ge(column, parse(value)?),
+            "like" => Filter::like(column, value),
+            "in" => Filter::in_list(
+                column,
+                value.split(',').map(parse).collect::<Result<Vec<_>>>()?,
+            ),
+            "null" => match FieldType::Bool.parse(value) {
+                Some(Value::Bool(true)) => Filter::is_null(column),
+                Some(Value::Bool(false)) => Filter::is_not_null(column),
+                _ => return InvalidParamSnafu { param: key, value }.fail(),
+            },
+            _ => return UnknownOperatorSnafu { op }.fail(),
+        })
+    }
+}
+
+impl FieldType {
+    /// parse a value from the query string
+    pub fn parse(&self, s: &str) -> Option<Value> {
+        match self {
+            FieldType::String => Some(s.into()),
+            FieldType::Int => s.parse::<i64>().ok().map(Value::Int),
+            FieldType::Float => s
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(Value::Float),
+            FieldType::Bool => match s {
+                "true" | "1" => Some(Value::Bool(true)),
+                "false" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// split `filter[field]` or `filter[field][op]` into the field and the operator
+fn parse_filter_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_prefix("filter[")?.strip_suffix(']')?;
+    let (field, op) = rest.split_once("][").unwrap_or((rest, "eq"));
+    (!field.contains(['[', ']']) && !op.contains(['[', ']'])).then_some((field, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cursor, Placeholder};
+    use anyhow::Result;
+    use std::collections::HashMap;
+
+    fn users() -> Result<Resource<'static>> {
+        Ok(ResourceBuilder::default()
+            .source("users")
+            .sortable(vec!["id".into(), "created_at".into()])
+            .filterable(vec![
+                ("status".into(), FieldType::String),
+                ("age".into(), FieldType::Int),
+                ("deleted_at".into(), FieldType::String),
+            ])
+            .order(vec![OrderBy::asc("id")])
+            .build()?)
+    }
+
+    #[test]
+    fn resource_should_parse_query_string() -> Result<()> {
+        let cursor = Cursor::Offset(20).encode();
+        let query = users()?.parse_query(&format!(
+            "?page_size=20&cursor={cursor}&sort=-created_at,id&filter[status]=active&filter[age][gte]=18&filter[deleted_at][null]=true&utm_source=x"
+        ))?;
+        assert_eq!(query.page_size, 20);
+        assert_eq!(query.cursor.as_deref(), Some(cursor.as_str()));
+
+        let stmt = query.to_sql_with_params(Placeholder::Dollar);
+        assert_eq!(
+            stmt.sql,
+            "SELECT * FROM users WHERE age >= $1 AND deleted_at IS NULL AND status = $2 ORDER BY created_at DESC, id LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(
+            stmt.params,
+            vec![18.into(), "active".into(), Value::Int(21), Value::Int(20)]
+        );
+
+        // percent-encoded values, the default order and page size
+        let query =
+            users()?.parse_query("filter%5Bstatus%5D%5Bin%5D=a%2Cb&filter[status][like]=x%25")?;
+        let stmt = query.to_sql_with_params(Placeholder::Dollar);
+        assert_eq!(
+            stmt.sql,
+            "SELECT * FROM users WHERE status IN ($1, $2) AND status LIKE $3 ORDER BY id LIMIT $4 OFFSET $5"
+        );
+        assert_eq!(
+            stmt.params,
+            vec![
+                "a".into(),
+                "b".into(),
+                "x%".into(),
+                Value::Int(11),
+                Value::Int(0)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resource_should_parse_params_map() -> Result<()> {
+        let params = HashMap::from([
+            ("sort".to_owned(), "created_at".to_owned()),
+            ("filter[age][lt]".to_owned(), "65".to_owned()),
+            ("filter[status][ne]".to_owned(), "banned".to_owned()),
+        ]);
+        let query = users()?.parse_params(&params)?;
+        let stmt = query.to_sql_with_params(Placeholder::Dollar);
+        assert_eq!(
+            stmt.sql,
+            "SELECT * FROM users WHERE age < $1 AND status <> $2 ORDER BY created_at, id LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(
+            stmt.params,
+            vec![65.into(), "banned".into(), Value::Int(11), Value::Int(0)]
+        );
+
+        // the default order stays the tiebreaker, look-alike parameters are ignored
+        let query = users()?.parse_query("sort=-created_at&filters=x&filter=y")?;
+        assert_eq!(
+            query.to_sql_with_params(Placeholder::Dollar).sql,
+            "SELECT * FROM users ORDER BY created_at DESC, id LIMIT $1 OFFSET $2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resource_should_reject_bad_params() -> Result<()> {
+        let resource = users()?;
+        let err = |q: &str| resource.parse_query(q).unwrap_err().to_string();
+
+        assert_eq!(err("sort=-name"), "Field cannot be sorted: name");
+        assert_eq!(err("filter[name]=x"), "Field cannot be filtered: name");
+        assert_eq!(
+            err("filter[age][regex]=1"),
+            "Unknown filter operator: regex"
+        );
+        assert_eq!(err("filter[age][like]=1%"), "Unknown filter operator: like");
+        assert_eq!(
+            err("filter[age]=abc"),
+            "Invalid value for query parameter filter[age]: abc"
+        );
+        assert_eq!(
+            err("filter[age][in]=1,x"),
+            "Invalid value for query parameter filter[age][in]: x"
+        );
+        assert_eq!(
+            err("filter[age=1"),
+            "Invalid value for query parameter filter[age: 1"
+        );
+        assert_eq!(
+            err("page_size=-1"),
+            "Invalid value for query parameter page_size: -1"
+        );
+        assert!(resource.parse_query("cursor=abc!").is_err());
+        assert!(ResourceBuilder::default()
+            .source("users")
+            .sortable(vec!["id; --".into()])
+            .build()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn resource_should_be_validated_when_built() {
+        let builder = ResourceBuilder::default().source("users").clone();
+        assert!(builder.build().is_ok());
+        assert!(ResourceBuilder::default().build().is_err());
+        assert!(builder.clone().source("users; --").build().is_err());
+        assert!(builder
+            .clone()
+            .projection(vec!["id)".into()])
+            .build()
+            .is_err());
+        assert!(builder
+            .clone()
+            .order(vec![OrderBy::asc("id desc")])
+            .build()
+            .is_err());
+        assert!(builder.clone().mode(PageMode::Keyset).build().is_err());
+        let err = builder
+            .clone()
+            .page_policy(PagePolicy::new(10, 20, 5))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidPagePolicy));
+    }
+}
@@ -1,31 +1,223 @@
 use crate::{
     error::*,
-    utils::{decode_u64, encode_u64},
-    Container, PageInfo, Pager, Paginator,
+    order::{order_clause, seek_predicate},
+    statement::Binder,
+    Container, Cursor, CursorCodec, CursorEnvelope, Dialect, Filter, GenericDialect, Ident,
+    LimitStyle, OrderBy, PageInfo, PageNumber, Pager, Paginator, Placeholder, SortKey, Statement,
+    Total, Value,
 };
 use derive_builder::Builder;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use snafu::ensure;
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt, sync::OnceLock};
 
-const MAX_PAGE_SIZE: u64 = 100;
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageMode {
+    /// skip the number of items in the cursor (LIMIT/OFFSET)
+    #[default]
+    Offset,
+    /// seek past the sort key of the last row of the previous page
+    Keyset,
+}
+
+/// How to count the total number of items (see `SqlQuery::to_count_statement`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalPolicy {
+    /// `COUNT(*)` over all the matching rows
+    #[default]
+    Exact,
+    /// count up to `cap` rows. If there are more, the total is reported as at least `cap`
+    Capped { cap: u64 },
+    /// estimate from planner statistics if the dialect supports it and the query has no
+    /// filter, otherwise count up to `cap` rows
+    Estimated { cap: u64 },
+}
+
+/// deepest offset databases take, as a signed 64-bit integer
+const MAX_SQL_OFFSET: u64 = i64::MAX as u64;
+
+/// Page size limits. A page size of 0 means no size was given and the default size is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PagePolicy {
+    /// page size used when none is given
+    pub default_size: u64,
+    pub min_size: u64,
+    pub max_size: u64,
+    /// what to do with a page size out of range
+    pub out_of_range: OutOfRange,
+    /// deepest offset clients can ask for, to prevent deep-page scraping
+    pub max_offset: Option<u64>,
+    /// suggest keyset pagination in the error when the offset is too deep
+    pub suggest_keyset: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfRange {
+    /// use the closest size in range
+    #[default]
+    Clamp,
+    /// fail with `Error::InvalidPageSize`
+    Reject,
+}
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Builder)]
 #[builder(build_fn(name = "private_build"), setter(into, strip_option), default)]
 pub struct SqlQuery<'a> {
     /// source table or view
-    pub source: Cow<'a, str>,
+    pub source: Ident<'a>,
     /// fields to include in the result
-    pub projection: Vec<Cow<'a, str>>,
+    pub projection: Vec<Ident<'a>>,
     /// filter condition (the WHERE clause)
-    pub filter: Option<Cow<'a, str>>,
-    /// sort order (the ORDER BY clause)
-    pub order: Option<Cow<'a, str>>,
-    /// previous page cursor, in base64 (right now this is just the number of items to skip)
+    pub filter: Option<Filter<'a>>,
+    /// sort order (the ORDER BY clause), it also forms the sort key in keyset mode
+    pub order: Vec<OrderBy<'a>>,
+    /// pagination mode
+    pub mode: PageMode,
+    /// previous page cursor, in base64 (see `Cursor`)
     pub cursor: Option<Cow<'a, str>>,
     /// page size
     pub page_size: u64,
+    /// how to count the total number of items
+    #[serde(default)]
+    pub total: TotalPolicy,
+    /// page size limits. It is set by the server, so it is never deserialized
+    #[serde(skip)]
+    pub page_policy: PagePolicy,
+    /// encode and decode cursors, e.g. to sign them
+    #[serde(skip)]
+    pub codec: CursorCodec,
+    /// the cursor envelope verified or decrypted on first use
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    pub(crate) decoded: DecodedCursor,
+}
+
+/// A cursor envelope verified or decrypted once, so it is not done again each time the query is
+/// rendered. It is keyed by the encoded cursor and the codec, and dropped when the query is
+/// cloned. The fingerprint and the expiry are checked on each use, since the query or the time
+/// may have changed
+#[derive(Default)]
+pub(crate) struct DecodedCursor(OnceLock<(String, CursorCodec, CursorEnvelope)>);
+
+impl Clone for DecodedCursor {
+    fn clone(&self) -> Self {
+        // the cursor or the query it is bound to may change in the clone
+        Self::default()
+    }
+}
+
+impl PartialEq for DecodedCursor {
+    fn eq(&self, _: &Self) -> bool {
+        // a cache, not part of the query
+        true
+    }
+}
+
+impl Eq for DecodedCursor {}
+
+impl fmt::Debug for DecodedCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DecodedCursor").finish_non_exhaustive()
+    }
+}
+
+impl PagePolicy {
+    /// sizes out of range are clamped
+    pub fn new(default_size: u64, min_size: u64, max_size: u64) -> Self {
+        Self {
+            default_size,
+            min_size,
+            max_size,
+            out_of_range: OutOfRange::Clamp,
+            max_offset: None,
+            suggest_keyset: false,
+        }
+    }
+
+    /// reject offsets deeper than `max`. There is no next page beyond it
+    pub fn with_max_offset(self, max: u64) -> Self {
+        Self {
+            max_offset: Some(max),
+            ..self
+        }
+    }
+
+    /// suggest keyset pagination when the offset is too deep
+    pub fn suggest_keyset(self) -> Self {
+        Self {
+            suggest_keyset: true,
+            ..self
+        }
+    }
+
+    /// reject sizes out of range instead of clamping them
+    pub fn reject_out_of_range(self) -> Self {
+        Self {
+            out_of_range: OutOfRange::Reject,
+            ..self
+        }
+    }
+
+    /// use the default size for 0, and clamp the size if the policy says so
+    pub fn normalize(&self, size: u64) -> u64 {
+        match (size, self.out_of_range) {
+            (0, _) => self.default_size,
+            (size, OutOfRange::Clamp) => {
+                size.clamp(self.min_size, self.max_size.max(self.min_size))
+            }
+            (size, OutOfRange::Reject) => size,
+        }
+    }
+
+    /// make sure the size is in range
+    pub fn check(&self, size: u64) -> Result<(), Error> {
+        ensure!(
+            (self.min_size..=self.max_size).contains(&size),
+            InvalidPageSizeSnafu {
+                size,
+                min: self.min_size,
+                max: self.max_size
+            }
+        );
+        Ok(())
+    }
+
+    /// make sure the offset is not too deep, for the policy and for SQL
+    pub fn check_offset(&self, offset: u64) -> Result<(), Error> {
+        let max = self
+            .max_offset
+            .map_or(MAX_SQL_OFFSET, |max| max.min(MAX_SQL_OFFSET));
+        ensure!(
+            offset <= max,
+            OffsetTooDeepSnafu {
+                offset,
+                max,
+                suggest_keyset: self.suggest_keyset
+            }
+        );
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        ensure!(
+            1 <= self.min_size
+                && self.min_size <= self.default_size
+                && self.default_size <= self.max_size,
+            InvalidPagePolicySnafu
+        );
+        Ok(())
+    }
+}
+
+impl Default for PagePolicy {
+    fn default() -> Self {
+        Self::new(10, 1, 100)
+    }
 }
 
 impl<'a> SqlQueryBuilder<'a> {
@@ -41,112 +233,576 @@ impl<'a> SqlQueryBuilder<'a> {
 }
 
 impl<'a> SqlQuery<'a> {
+    /// Generate the SQL with all values inlined as literals, e.g. for logging or debugging. Some
+    /// values come from clients (cursors, filters), so run queries with `to_statement` instead
     pub fn to_sql(&self) -> String {
-        let limit = self.page_size + 1;
-        let offset = self.get_cursor().unwrap_or_default();
+        let dialect = GenericDialect::default();
+        self.render(&mut Binder::inline(&dialect))
+    }
 
-        let where_clause = if let Some(filter) = &self.filter {
-            Cow::Owned(format!("WHERE {filter}"))
-        } else {
-            Cow::Borrowed("")
+    /// Generate the SQL with placeholders in the given style, and the values to bind to them
+    /// (keyset values, page limit and offset) in order
+    pub fn to_sql_with_params(&self, placeholder: Placeholder) -> Statement {
+        self.to_statement(&GenericDialect(placeholder))
+    }
+
+    /// Generate the SQL for the given dialect, with the values to bind to its placeholders
+    pub fn to_statement(&self, dialect: &dyn Dialect) -> Statement {
+        let mut binder = Binder::params(dialect);
+        let sql = self.render(&mut binder);
+        binder.finish(sql)
+    }
+
+    /// Generate the `SELECT COUNT(*)` query matching this query (same source and filter, no
+    /// order or limit), with all values inlined as literals. Convert the result with `to_total`
+    /// and attach it to the pager with `Pager::with_total`
+    pub fn to_count_sql(&self) -> String {
+        let dialect = GenericDialect::default();
+        self.render_count(&mut Binder::inline(&dialect))
+    }
+
+    /// Generate the query counting the items for the given dialect according to the total
+    /// policy, with the values to bind
+    pub fn to_count_statement(&self, dialect: &dyn Dialect) -> Statement {
+        if let Some(stmt) = self.estimate_statement(dialect) {
+            return stmt;
+        }
+        let mut binder = Binder::params(dialect);
+        let sql = self.render_count(&mut binder);
+        binder.finish(sql)
+    }
+
+    /// Convert the result of the count query generated for the dialect into a total
+    pub fn to_total(&self, dialect: &dyn Dialect, count: u64) -> Total {
+        match self.total {
+            TotalPolicy::Exact => Total::exact(count),
+            TotalPolicy::Estimated { .. } if self.estimate_statement(dialect).is_some() => {
+                Total::estimate(count)
+            }
+            TotalPolicy::Capped { cap } | TotalPolicy::Estimated { cap } if count > cap => {
+                Total::at_least(cap)
+            }
+            _ => Total::exact(count),
+        }
+    }
+
+    fn render_count(&self, binder: &mut Binder) -> String {
+        let (TotalPolicy::Capped { cap } | TotalPolicy::Estimated { cap }) = self.total else {
+            let source = self.source.to_sql(binder.dialect);
+            return match &self.filter {
+                Some(filter) => format!(
+                    "SELECT COUNT(*) FROM {source} WHERE {}",
+                    filter.to_sql(binder)
+                ),
+                None => format!("SELECT COUNT(*) FROM {source}"),
+            };
         };
 
-        let order_clause = if let Some(order) = &self.order {
-            Cow::Owned(format!("ORDER BY {order}"))
-        } else {
+        // one more row than the cap tells whether there are more
+        let limit = Value::Int(i64::try_from(cap).unwrap_or(i64::MAX).saturating_add(1));
+        let style = binder.dialect.limit_style();
+        let top = match style {
+            LimitStyle::Top => format!("TOP ({}) ", binder.bind(&limit)),
+            _ => String::new(),
+        };
+        let source = self.source.to_sql(binder.dialect);
+        let filter = match (&self.filter, style) {
+            (Some(filter), LimitStyle::RowNum) => {
+                format!(" WHERE {} AND", filter.to_operand(binder))
+            }
+            (Some(filter), _) => format!(" WHERE {}", filter.to_sql(binder)),
+            (None, LimitStyle::RowNum) => " WHERE".to_owned(),
+            (None, _) => String::new(),
+        };
+        let limit = match style {
+            LimitStyle::LimitOffset => format!(" LIMIT {}", binder.bind(&limit)),
+            LimitStyle::OffsetFetch => format!(" FETCH FIRST {} ROWS ONLY", binder.bind(&limit)),
+            LimitStyle::RowNum => format!(" ROWNUM <= {}", binder.bind(&limit)),
+            LimitStyle::Top => String::new(),
+        };
+        // columns of derived tables must be named on SQL Server
+        format!("SELECT COUNT(*) FROM (SELECT {top}1 AS one FROM {source}{filter}{limit}) t")
+    }
+
+    /// the statement estimating the total from planner statistics, if the policy asks for it
+    /// and it can be estimated
+    fn estimate_statement(&self, dialect: &dyn Dialect) -> Option<Statement> {
+        match (&self.total, &self.filter, &self.source) {
+            (TotalPolicy::Estimated { .. }, None, Ident::Name(name)) => {
+                let (schema, table) = match name.rsplit_once('.') {
+                    Some((schema, table)) => (Some(schema), table),
+                    None => (None, name.as_ref()),
+                };
+                dialect.estimate_count(schema, table)
+            }
+            _ => None,
+        }
+    }
+
+    /// render the SELECT statement. Values are bound in the order they appear in the SQL text
+    fn render(&self, binder: &mut Binder) -> String {
+        let limit = i64::try_from(self.page_size).map_or(i64::MAX, |v| v.saturating_add(1));
+        let offset = match self.mode {
+            PageMode::Keyset => None,
+            PageMode::Offset => Some(i64::try_from(self.offset().unwrap_or(0)).unwrap_or(i64::MAX)),
+        };
+        // ROWNUM counts the rows up to the end of the page
+        let end = Value::Int(limit.saturating_add(offset.unwrap_or(0)));
+        let (limit, offset) = (Value::Int(limit), offset.map(Value::Int));
+        let style = binder.dialect.limit_style();
+        let cursor = self.keyset_cursor();
+
+        let top_clause = match (style, &offset) {
+            (LimitStyle::Top, None) => Cow::Owned(format!("TOP ({})", binder.bind(&limit))),
+            _ => Cow::Borrowed(""),
+        };
+
+        // paging backwards: reverse the order and seek before the first row of the next page
+        let order = match &cursor {
+            Some(Cursor::Before(_)) => {
+                Cow::Owned(self.order.iter().map(OrderBy::reversed).collect())
+            }
+            _ => Cow::Borrowed(&self.order[..]),
+        };
+        // the filter is AND-ed with the keyset seek predicate. Without values the cursor points to
+        // the first or the last page, there is nothing to seek past
+        let keyset = cursor
+            .as_ref()
+            .and_then(Cursor::keyset)
+            .filter(|values| !values.is_empty());
+        let filter = self.filter.as_ref().map(|filter| match keyset {
+            Some(_) => filter.to_operand(binder),
+            None => filter.to_sql(binder),
+        });
+        let seek = keyset.map(|values| seek_predicate(&order, values, binder));
+        let conditions = filter.into_iter().chain(seek).collect::<Vec<_>>();
+
+        let where_clause = if conditions.is_empty() {
             Cow::Borrowed("")
+        } else {
+            Cow::Owned(format!("WHERE {}", conditions.join(" AND ")))
         };
 
-        [
+        let order_clause = match (order.is_empty(), style, &offset) {
+            (false, _, _) => {
+                Cow::Owned(format!("ORDER BY {}", order_clause(&order, binder.dialect)))
+            }
+            // OFFSET requires ORDER BY in this style
+            (true, LimitStyle::Top, Some(_)) => Cow::Borrowed("ORDER BY (SELECT NULL)"),
+            (true, _, _) => Cow::Borrowed(""),
+        };
+
+        let limit_clause = match (style, &offset) {
+            (LimitStyle::LimitOffset, None) => format!("LIMIT {}", binder.bind(&limit)),
+            (LimitStyle::LimitOffset, Some(offset)) => {
+                let limit = binder.bind(&limit);
+                format!("LIMIT {limit} OFFSET {}", binder.bind(offset))
+            }
+            (LimitStyle::OffsetFetch, None) => {
+                format!("FETCH FIRST {} ROWS ONLY", binder.bind(&limit))
+            }
+            (LimitStyle::OffsetFetch | LimitStyle::Top, Some(offset)) => {
+                let offset = binder.bind(offset);
+                format!(
+                    "OFFSET {offset} ROWS FETCH NEXT {} ROWS ONLY",
+                    binder.bind(&limit)
+                )
+            }
+            (LimitStyle::Top, None) | (LimitStyle::RowNum, _) => String::new(),
+        };
+
+        let sql = [
             "SELECT",
-            &self.projection(),
+            &top_clause,
+            &self.projection(binder.dialect),
             "FROM",
-            &self.source,
+            &self.source.to_sql(binder.dialect),
             &where_clause,
             &order_clause,
-            "LIMIT",
-            &limit.to_string(),
-            "OFFSET",
-            &offset.to_string(),
+            &limit_clause,
         ]
         .iter()
         .filter(|s| !s.is_empty())
-        .join(" ")
+        .join(" ");
+
+        match (style, &offset) {
+            (LimitStyle::RowNum, None) => {
+                format!(
+                    "SELECT * FROM ({sql}) WHERE ROWNUM <= {}",
+                    binder.bind(&end)
+                )
+            }
+            (LimitStyle::RowNum, Some(offset)) => {
+                let end = binder.bind(&end);
+                format!(
+                    "SELECT * FROM (SELECT t.*, ROWNUM rn FROM ({sql}) t WHERE ROWNUM <= {end}) WHERE rn > {}",
+                    binder.bind(offset)
+                )
+            }
+            _ => sql,
+        }
+    }
+
+    /// Get the pager for the fetched data (page_size + 1 rows), the extra row is removed.
+    /// Only offset mode is supported, keyset mode needs the sort keys of the rows (see
+    /// `get_keyset_pager`).
+    pub fn get_pager<T: Container>(&self, data: &mut T) -> Result<Pager<Cursor>, Error> {
+        ensure!(self.mode == PageMode::Offset, MissingSortKeySnafu);
+        // stay in the same snapshot when paging through one
+        let snapshot = match self.get_cursor() {
+            Ok(Some(Cursor::Snapshot { id, .. })) => Some(id),
+            _ => None,
+        };
+        let pager = self.page_info().get_pager(data)?;
+        Ok(pager.map(|offset| match &snapshot {
+            Some(id) => Cursor::Snapshot {
+                id: id.clone(),
+                offset,
+            },
+            None => Cursor::Offset(offset),
+        }))
     }
 
-    pub fn get_pager<T: Container>(&self, data: &mut T) -> Pager {
-        let page_info = self.page_info();
-        page_info.get_pager(data)
+    /// Like `get_pager`, but supports both modes. When paging backwards in keyset mode the rows
+    /// are flipped back into display order.
+    pub fn get_keyset_pager<T>(&self, data: &mut T) -> Result<Pager<Cursor>, Error>
+    where
+        T: Container,
+        T::Item: SortKey,
+    {
+        match self.mode {
+            PageMode::Offset => self.get_pager(data),
+            PageMode::Keyset => Ok(self.seek_pager(data)),
+        }
     }
 
-    pub fn get_cursor(&self) -> Option<u64> {
-        self.cursor.as_deref().and_then(|c| decode_u64(c).ok())
+    /// decode the cursor, verifying its signature if the codec signs cursors, and that it was
+    /// issued for this query
+    pub fn get_cursor(&self) -> Result<Option<Cursor>, Error> {
+        let Some(s) = self.cursor.as_deref() else {
+            return Ok(None);
+        };
+        let envelope = match self.decoded.0.get() {
+            Some((encoded, codec, envelope)) if encoded == s && *codec == self.codec => {
+                envelope.clone()
+            }
+            _ => {
+                let envelope = self.codec.open_envelope(s)?;
+                // the first envelope is kept if the cursor or the codec was changed since
+                let _ = self
+                    .decoded
+                    .0
+                    .set((s.to_owned(), self.codec.clone(), envelope.clone()));
+                envelope
+            }
+        };
+        self.check_envelope(envelope, s).map(Some)
     }
 
-    pub fn next_page(&self, pager: &Pager) -> Option<Self> {
-        let page_info = self.page_info();
-        let page_info = page_info.next_page(pager);
-        page_info.map(|page_info| Self {
-            source: self.source.clone(),
-            projection: self.projection.clone(),
-            filter: self.filter.clone(),
-            order: self.order.clone(),
-            cursor: page_info.cursor.map(|c| encode_u64(c).into()),
-            page_size: page_info.page_size,
-        })
+    /// decode a cursor encoded by `encode_cursor`, e.g. the cursor of an edge
+    pub fn decode_cursor(&self, s: &str) -> Result<Cursor, Error> {
+        let envelope = self.codec.open_envelope(s)?;
+        self.check_envelope(envelope, s)
     }
 
-    pub fn validate(&self) -> Result<(), Error> {
+    /// check that the cursor has not expired and was issued for this query
+    fn check_envelope(&self, envelope: CursorEnvelope, s: &str) -> Result<Cursor, Error> {
+        self.codec.check_expiry(&envelope, s)?;
+        // legacy cursors are not bound to a query
         ensure!(
-            self.page_size > 0 && self.page_size < MAX_PAGE_SIZE,
-            InvalidPageSizeSnafu {
-                size: self.page_size
-            }
+            envelope.fingerprint.is_none_or(|v| v == self.fingerprint()),
+            CursorMismatchSnafu { cursor: s }
         );
+        Ok(envelope.cursor)
+    }
+
+    /// encode a cursor of the pager for the client. It is bound to the fingerprint of this query,
+    /// and signed if the codec signs cursors
+    pub fn encode_cursor(&self, cursor: &Cursor) -> String {
+        self.codec.encode_envelope(&CursorEnvelope {
+            fingerprint: Some(self.fingerprint()),
+            ..CursorEnvelope::new(cursor.clone())
+        })
+    }
+
+    /// hash of the source, filter, order and projection. Cursors issued for a query are only
+    /// valid for queries with the same fingerprint
+    pub fn fingerprint(&self) -> u64 {
+        let dialect = GenericDialect::default();
+        let filter = self
+            .filter
+            .as_ref()
+            .map(|filter| filter.to_sql(&mut Binder::inline(&dialect)))
+            .unwrap_or_default();
+        let parts = [
+            self.source.to_sql(&dialect),
+            self.projection(&dialect).into_owned(),
+            filter,
+            order_clause(&self.order, &dialect),
+        ];
+
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update(part);
+            hasher.update([0]);
+        }
+        let digest = hasher.finalize();
+        u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+    }
+
+    pub fn next_page(&self, pager: &Pager<Cursor>) -> Option<Self> {
+        pager.next.as_ref().map(|cursor| self.with_cursor(cursor))
+    }
+
+    pub fn prev_page(&self, pager: &Pager<Cursor>) -> Option<Self> {
+        pager.prev.as_ref().map(|cursor| self.with_cursor(cursor))
+    }
+
+    /// the first page. In keyset mode it starts from the first row
+    pub fn first_page(&self) -> Self {
+        let cursor = match self.mode {
+            PageMode::Keyset => Cursor::After(Vec::new()),
+            PageMode::Offset => self.offset_cursor(0),
+        };
+        self.with_cursor(&cursor)
+    }
+
+    /// the last page. In keyset mode it is read backwards from the last row, in offset mode the
+    /// exact total of the pager is needed (see `Pager::with_total`)
+    pub fn last_page(&self, pager: &Pager<Cursor>) -> Option<Self> {
+        if let Some(cursor) = &pager.last {
+            return Some(self.with_cursor(cursor));
+        }
+        match self.mode {
+            PageMode::Keyset => Some(self.with_cursor(&Cursor::Before(Vec::new()))),
+            PageMode::Offset => {
+                let offset = self.page_info().last_offset(pager.total)?;
+                Some(self.with_cursor(&self.offset_cursor(offset)))
+            }
+        }
+    }
+
+    /// attach the total to the pager, e.g. from `to_total`. In offset mode an exact total also
+    /// gives the last page, unless it is the current one
+    pub fn pager_with_total(&self, pager: Pager<Cursor>, total: impl Into<Total>) -> Pager<Cursor> {
+        let pager = pager.with_total(total);
+        let last = pager.last.clone().or_else(|| self.last_cursor(pager.total));
+        Pager { last, ..pager }
+    }
+
+    /// cursor of the last page in offset mode, if the total is exact and it is not the current
+    /// page
+    pub(crate) fn last_cursor(&self, total: Option<Total>) -> Option<Cursor> {
+        match self.mode {
+            PageMode::Keyset => None,
+            PageMode::Offset => self
+                .page_info()
+                .last_link(total)
+                .map(|offset| self.offset_cursor(offset)),
+        }
+    }
+
+    /// jump to the page number (starting from 1). Only pages by offset within the max offset
+    /// of the page policy can be jumped to
+    pub fn jump_to_page(&self, page: u64) -> Option<Self> {
+        let offset = PageNumber::new(page, self.page_size).offset().ok()?;
+        if self.mode == PageMode::Keyset || self.page_policy.check_offset(offset).is_err() {
+            return None;
+        }
+        Some(self.with_cursor(&self.offset_cursor(offset)))
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        self.page_policy.validate()?;
+        self.page_policy.check(self.page_size)?;
+        self.codec.validate()?;
         ensure!(!self.source.is_empty(), InvalidSourceSnafu);
+        self.source.validate()?;
+        for ident in &self.projection {
+            ident.validate_projection()?;
+        }
+        for order in &self.order {
+            order.column.validate()?;
+        }
+        if let Some(filter) = &self.filter {
+            filter.validate()?;
+        }
+
+        let cursor = self.get_cursor()?;
+        match self.mode {
+            PageMode::Offset => {
+                ensure!(
+                    cursor.as_ref().is_none_or(|c| c.offset().is_some()),
+                    InvalidCursorSnafu {
+                        cursor: self.cursor.as_deref().unwrap_or_default()
+                    }
+                );
+                if let Some(offset) = cursor.as_ref().and_then(Cursor::offset) {
+                    self.page_policy.check_offset(offset)?;
+                }
+            }
+            PageMode::Keyset => {
+                ensure!(!self.order.is_empty(), InvalidKeysetSnafu);
+                ensure!(
+                    match cursor.as_ref().map(Cursor::keyset) {
+                        None => true,
+                        Some(Some(values)) => {
+                            values.is_empty() || values.len() == self.order.len()
+                        }
+                        Some(None) => false,
+                    },
+                    InvalidCursorSnafu {
+                        cursor: self.cursor.as_deref().unwrap_or_default()
+                    }
+                );
+            }
+        }
 
         Ok(())
     }
 
+    /// use the default page size if none is given, and clamp it if the page policy says so
     pub fn normalize(&mut self) {
-        if self.page_size == 0 {
-            self.page_size = 10;
-        } else if self.page_size > MAX_PAGE_SIZE {
-            self.page_size = MAX_PAGE_SIZE;
-        }
+        self.page_size = self.page_policy.normalize(self.page_size);
     }
 
     fn page_info(&self) -> PageInfo {
         PageInfo {
-            cursor: self.get_cursor(),
+            cursor: self.offset(),
             page_size: self.page_size,
+            max_offset: self.page_policy.max_offset,
+        }
+    }
+
+    pub(crate) fn offset(&self) -> Option<u64> {
+        self.get_cursor()
+            .ok()
+            .flatten()
+            .as_ref()
+            .and_then(Cursor::offset)
+    }
+
+    /// the keyset cursor in keyset mode, if it matches the sort order. Like a bad offset cursor,
+    /// a bad keyset cursor of an unvalidated (e.g. deserialized) query is ignored
+    fn keyset_cursor(&self) -> Option<Cursor> {
+        if self.mode != PageMode::Keyset {
+            return None;
+        }
+        self.get_cursor().ok().flatten().filter(|cursor| {
+            cursor
+                .keyset()
+                .is_some_and(|values| values.is_empty() || values.len() == self.order.len())
+        })
+    }
+
+    /// cursor for the offset, staying in the same snapshot when paging through one
+    pub(crate) fn offset_cursor(&self, offset: u64) -> Cursor {
+        match self.get_cursor() {
+            Ok(Some(Cursor::Snapshot { id, .. })) => Cursor::Snapshot { id, offset },
+            _ => Cursor::Offset(offset),
+        }
+    }
+
+    pub(crate) fn with_cursor(&self, cursor: &Cursor) -> Self {
+        Self {
+            cursor: Some(self.encode_cursor(cursor).into()),
+            ..self.clone()
         }
     }
 
-    fn projection(&self) -> Cow<'a, str> {
+    fn seek_pager<T>(&self, data: &mut T) -> Pager<Cursor>
+    where
+        T: Container,
+        T::Item: SortKey,
+    {
+        let cursor = self.keyset_cursor();
+        let backward = matches!(cursor, Some(Cursor::Before(_)));
+
+        // rows were fetched in reversed order, the extra row is at the front after flipping
+        if backward {
+            data.reverse();
+        }
+        let has_more = data.len() as u64 > self.page_size;
+        if has_more {
+            if backward {
+                data.pop_front();
+            } else {
+                data.pop();
+            }
+        }
+
+        let before_first = || data.first().map(|item| Cursor::Before(self.sort_key(item)));
+        let after_last = || data.last().map(|item| Cursor::After(self.sort_key(item)));
+        let (prev, next) = match cursor {
+            Some(Cursor::Before(values)) if values.is_empty() => {
+                (has_more.then(before_first).flatten(), None)
+            }
+            Some(Cursor::After(values)) if !values.is_empty() => {
+                (before_first(), has_more.then(after_last).flatten())
+            }
+            Some(Cursor::Before(_)) => (has_more.then(before_first).flatten(), after_last()),
+            _ => (None, has_more.then(after_last).flatten()),
+        };
+
+        Pager {
+            first: prev.is_some().then(|| Cursor::After(Vec::new())),
+            last: next.is_some().then(|| Cursor::Before(Vec::new())),
+            prev,
+            next,
+            total: None,
+            page_size: self.page_size,
+            page: None,
+        }
+    }
+
+    pub(crate) fn sort_key(&self, item: &impl SortKey) -> Vec<Value> {
+        self.order
+            .iter()
+            .map(|o| item.sort_key(o.column.as_str()))
+            .collect()
+    }
+
+    fn projection(&self, dialect: &dyn Dialect) -> Cow<'a, str> {
         if self.projection.is_empty() {
             return "*".into();
         }
 
-        self.projection.iter().join(", ").into()
+        self.projection
+            .iter()
+            .map(|ident| ident.to_sql(dialect))
+            .join(", ")
+            .into()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pager_test_utils::generate_test_ids;
+    use crate::{
+        pager_test_utils::generate_test_ids, utils::encode_u64, MsSqlDialect, MySqlDialect,
+        PostgresDialect, SqliteDialect,
+    };
     use anyhow::{Context, Result};
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
 
     #[test]
     fn sql_query_should_generate_right_sql() -> Result<()> {
         let query = SqlQuery {
             source: "users".into(),
             projection: vec!["id".into(), "name".into()],
-            filter: Some("id > 10".into()),
-            order: Some("id DESC".into()),
+            filter: Some(Filter::raw("id > 10")),
+            order: vec![OrderBy::desc("id")],
             cursor: Some(encode_u64(10).into()),
             page_size: 10,
+            ..Default::default()
         };
 
         let sql = query.to_sql();
@@ -158,14 +814,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sql_query_should_generate_params() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .filter(Filter::is_null("deleted_at"))
+            .order(vec![
+                OrderBy::desc("created_at"),
+                OrderBy::asc("id").not_null(),
+            ])
+            .mode(PageMode::Keyset)
+            .cursor(Cursor::After(vec!["2023-02-06".into(), 42.into()]).encode())
+            .build()?;
+
+        let stmt = query.to_sql_with_params(Placeholder::Dollar);
+        assert_eq!(
+            stmt.sql,
+            "SELECT * FROM users WHERE deleted_at IS NULL AND (created_at < $1 OR (created_at = $2 AND id > $3)) ORDER BY created_at DESC, id LIMIT $4"
+        );
+        assert_eq!(
+            stmt.params,
+            vec![
+                "2023-02-06".into(),
+                "2023-02-06".into(),
+                42.into(),
+                Value::Int(11)
+            ]
+        );
+
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .cursor(Cursor::Offset(20).encode())
+            .build()?;
+        let placeholders = [
+            (Placeholder::Dollar, "LIMIT $1 OFFSET $2"),
+            (Placeholder::Question, "LIMIT ? OFFSET ?"),
+            (Placeholder::At, "LIMIT @p1 OFFSET @p2"),
+            (Placeholder::Colon, "LIMIT :p1 OFFSET :p2"),
+        ];
+        for (placeholder, expected) in placeholders {
+            let stmt = query.to_sql_with_params(placeholder);
+            assert_eq!(stmt.sql, format!("SELECT * FROM users {expected}"));
+            assert_eq!(stmt.params, vec![Value::Int(11), Value::Int(20)]);
+        }
+        Ok(())
+    }
+
     #[test]
     fn sql_builder_should_get_correct_page_info() -> Result<()> {
         let query = SqlQueryBuilder::default().source("users").build()?;
 
         let mut data = generate_test_ids(1, 11);
-        let pager = query.get_pager(&mut data);
+        let pager = query.get_pager(&mut data)?;
         assert_eq!(pager.prev, None);
-        assert_eq!(pager.next, Some(10));
+        assert_eq!(pager.next, Some(Cursor::Offset(10)));
 
         let query = query.next_page(&pager).context("no next page")?;
         let sql = query.to_sql();
@@ -173,12 +875,679 @@ mod tests {
 
         // second page
         let mut data = generate_test_ids(11, 21);
-        let pager = query.get_pager(&mut data);
-        assert_eq!(pager.prev, Some(0));
-        assert_eq!(pager.next, Some(20));
+        let pager = query.get_pager(&mut data)?;
+        assert_eq!(pager.prev, Some(Cursor::Offset(0)));
+        assert_eq!(pager.next, Some(Cursor::Offset(20)));
         let query = query.next_page(&pager).context("no next page")?;
         let sql = query.to_sql();
         assert_eq!(sql, "SELECT * FROM users LIMIT 11 OFFSET 20");
+
+        // rows without sort keys page by offset
+        let mut rows: Vec<String> = (0..3).map(|i| i.to_string()).collect();
+        let pager = query.get_pager(&mut rows)?;
+        assert_eq!(pager.prev, Some(Cursor::Offset(10)));
+        assert_eq!(pager.next, None);
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_query_should_seek_past_last_row() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .filter(Filter::raw("name IS NOT NULL"))
+            .order(vec![OrderBy::asc("id").not_null()])
+            .mode(PageMode::Keyset)
+            .build()?;
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM users WHERE name IS NOT NULL ORDER BY id LIMIT 11"
+        );
+
+        let mut data = generate_test_ids(1, 11);
+        // seeking needs the sort keys of the rows
+        assert!(matches!(
+            query.get_pager(&mut data),
+            Err(Error::MissingSortKey)
+        ));
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(data.len(), 10);
+        assert_eq!(pager.next, Some(Cursor::After(vec![Value::Int(10)])));
+
+        let query = query.next_page(&pager).context("no next page")?;
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM users WHERE (name IS NOT NULL) AND id > 10 ORDER BY id LIMIT 11"
+        );
+
+        // last page
+        let mut data = generate_test_ids(11, 15);
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert!(pager.next.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_query_should_use_row_value_for_multiple_keys() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("events")
+            .order(vec![
+                OrderBy::asc("created_at").not_null(),
+                OrderBy::asc("id").not_null(),
+            ])
+            .mode(PageMode::Keyset)
+            .cursor(Cursor::After(vec!["2023-02-06".into(), 42.into()]).encode())
+            .build()?;
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM events WHERE (created_at, id) > ('2023-02-06', 42) ORDER BY created_at, id LIMIT 11"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_query_should_page_backwards() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("logs")
+            .order(vec![OrderBy::asc("id")])
+            .mode(PageMode::Keyset)
+            .cursor(Cursor::After(vec![10.into()]).encode())
+            .build()?;
+
+        // second page: 11..=20, there is a previous page
+        let mut data = generate_test_ids(11, 21);
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(pager.prev, Some(Cursor::Before(vec![11.into()])));
+        assert_eq!(pager.next, Some(Cursor::After(vec![20.into()])));
+
+        let query = query.prev_page(&pager).context("no prev page")?;
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM logs WHERE id < 11 ORDER BY id DESC LIMIT 11"
+        );
+
+        // rows come back in reversed order, only 10 exist before id 11
+        let mut data: Vec<_> = generate_test_ids(1, 10).into_iter().rev().collect();
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(data.len(), 10);
+        assert_eq!(data.first().map(|v| v.sort_key("id")), Some(1.into()));
+        assert_eq!(data.last().map(|v| v.sort_key("id")), Some(10.into()));
+        assert_eq!(pager.prev, None);
+        assert_eq!(pager.next, Some(Cursor::After(vec![10.into()])));
+
+        // paging backwards with more rows available trims the row furthest back
+        let query = query.with_cursor(&Cursor::Before(vec![22.into()]));
+        let mut data: Vec<_> = generate_test_ids(11, 21).into_iter().rev().collect();
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(data.first().map(|v| v.sort_key("id")), Some(12.into()));
+        assert_eq!(pager.prev, Some(Cursor::Before(vec![12.into()])));
+        assert_eq!(pager.next, Some(Cursor::After(vec![21.into()])));
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_query_should_handle_mixed_directions_and_nulls() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![
+                OrderBy::desc("last_login").nulls_last(),
+                OrderBy::asc("id").not_null(),
+            ])
+            .mode(PageMode::Keyset)
+            .cursor(Cursor::After(vec!["2023-02-06".into(), 42.into()]).encode())
+            .build()?;
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM users WHERE ((last_login < '2023-02-06' OR last_login IS NULL) OR (last_login = '2023-02-06' AND id > 42)) ORDER BY last_login DESC NULLS LAST, id LIMIT 11"
+        );
+
+        let query = query.with_cursor(&Cursor::Before(vec![Value::Null, 42.into()]));
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM users WHERE (last_login IS NOT NULL OR (last_login IS NULL AND id < 42)) ORDER BY last_login NULLS FIRST, id DESC LIMIT 11"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sql_query_should_bind_filter_values() -> Result<()> {
+        let tenant = Filter::eq("tenant_id", 7);
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .filter(Filter::in_list("status", ["active", "invited"]).and(tenant))
+            .order(vec![OrderBy::asc("id").not_null()])
+            .mode(PageMode::Keyset)
+            .cursor(Cursor::After(vec![100.into()]).encode())
+            .build()?;
+
+        let stmt = query.to_statement(&PostgresDialect);
+        assert_eq!(
+            stmt.sql,
+            r#"SELECT * FROM "users" WHERE ("status" IN ($1, $2) AND "tenant_id" = $3) AND "id" > $4 ORDER BY "id" LIMIT $5"#
+        );
+        assert_eq!(
+            stmt.params,
+            vec![
+                "active".into(),
+                "invited".into(),
+                7.into(),
+                100.into(),
+                11.into()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unvalidated_query_should_ignore_bad_keyset_cursor() -> Result<()> {
+        // an offset query doesn't seek, even without a sort order
+        let query: SqlQuery = serde_json::from_value(serde_json::json!({
+            "source": "users",
+            "projection": [],
+            "order": [],
+            "mode": "offset",
+            "page_size": 10,
+            "cursor": Cursor::After(vec![1.into()]).encode(),
+        }))?;
+        assert_eq!(query.to_sql(), "SELECT * FROM users LIMIT 11 OFFSET 0");
+
+        // a keyset cursor with fewer values than sort columns
+        let query = SqlQuery {
+            mode: PageMode::Keyset,
+            order: vec![OrderBy::asc("a"), OrderBy::asc("b")],
+            ..query
+        };
+        assert!(query.validate().is_err());
+        assert_eq!(query.to_sql(), "SELECT * FROM users ORDER BY a, b LIMIT 11");
+        assert_eq!(
+            query.get_keyset_pager(&mut generate_test_ids(1, 11))?.prev,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sql_query_should_validate_identifiers() -> Result<()> {
+        let builder = SqlQueryBuilder::default().source("users").clone();
+        assert!(builder
+            .clone()
+            .projection(vec!["id; DROP TABLE users".into()])
+            .build()
+            .is_err());
+        assert!(builder
+            .clone()
+            .source("users u JOIN admins a")
+            .build()
+            .is_err());
+        assert!(builder.clone().source("users.*").build().is_err());
+
+        // names are validated when deserialized too
+        let query = serde_json::from_value::<SqlQuery>(serde_json::json!({
+            "source": "users; DROP TABLE users; --",
+            "projection": [],
+            "order": [],
+            "mode": "offset",
+            "page_size": 10,
+        }));
+        assert!(query.is_err());
+        assert!(builder
+            .clone()
+            .order(vec![OrderBy::asc("id)--")])
+            .build()
+            .is_err());
+        assert!(builder
+            .clone()
+            .filter(Filter::eq("1=1 OR id", 1))
+            .build()
+            .is_err());
+
+        let query = builder
+            .clone()
+            .source("public.users")
+            .projection(vec!["users.*".into(), Ident::raw("count(*) AS n")])
+            .build()?;
+        assert_eq!(
+            query.to_statement(&PostgresDialect).sql,
+            r#"SELECT "users".*, count(*) AS n FROM "public"."users" LIMIT $1 OFFSET $2"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inline_sql_should_not_be_injectable() -> Result<()> {
+        let keyset = |cursor: Cursor| {
+            SqlQueryBuilder::default()
+                .source("users")
+                .order(vec![OrderBy::asc("name").not_null()])
+                .mode(PageMode::Keyset)
+                .cursor(cursor.encode())
+                .build()
+        };
+        let query = keyset(Cursor::After(vec!["\\' OR 1=1 -- ".into()]))?;
+        assert_eq!(
+            query.to_sql(),
+            r"SELECT * FROM users WHERE name > '\\'' OR 1=1 -- ' ORDER BY name LIMIT 11"
+        );
+        assert!(keyset(Cursor::After(vec![f64::INFINITY.into()])).is_err());
+        assert!(keyset(Cursor::After(vec![f64::NAN.into()])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_query_should_reject_mismatched_cursor() {
+        let builder = SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![OrderBy::asc("id")])
+            .mode(PageMode::Keyset)
+            .clone();
+
+        let cursor = Cursor::Offset(10).encode();
+        assert!(builder.clone().cursor(cursor).build().is_err());
+
+        let cursor = Cursor::After(vec![1.into(), 2.into()]).encode();
+        assert!(builder.clone().cursor(cursor).build().is_err());
+
+        assert!(builder.clone().order(vec![]).build().is_err());
+    }
+
+    #[test]
+    fn signed_query_should_verify_cursor() -> Result<()> {
+        let codec = CursorCodec::signed("k1", SECRET)?;
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .codec(codec.clone())
+            .build()?;
+
+        let mut data = generate_test_ids(1, 11);
+        let pager = query.get_pager(&mut data)?;
+        let next = query.next_page(&pager).context("no next page")?;
+        assert_eq!(next.to_sql(), "SELECT * FROM users LIMIT 11 OFFSET 10");
+        assert_eq!(
+            next.cursor.as_deref(),
+            Some(query.encode_cursor(&Cursor::Offset(10)).as_str())
+        );
+
+        // forged or unsigned cursors are rejected
+        let builder = SqlQueryBuilder::default()
+            .source("users")
+            .codec(codec)
+            .clone();
+        let err = builder
+            .clone()
+            .cursor(Cursor::Offset(1_000_000).encode())
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn query_should_reject_cursor_of_another_query() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .filter(Filter::eq("status", "a"))
+            .order(vec![OrderBy::asc("id")])
+            .build()?;
+        let cursor = query.encode_cursor(&Cursor::Offset(10));
+        let builder = SqlQueryBuilder::default()
+            .source("users")
+            .filter(Filter::eq("status", "a"))
+            .order(vec![OrderBy::asc("id")])
+            .cursor(cursor)
+            .clone();
+        assert_eq!(builder.build()?.get_cursor()?, Some(Cursor::Offset(10)));
+
+        let queries = [
+            builder.clone().filter(Filter::eq("status", "b")).clone(),
+            builder.clone().order(vec![OrderBy::desc("id")]).clone(),
+            builder.clone().projection(vec!["id".into()]).clone(),
+            builder.clone().source("orders").clone(),
+        ];
+        for builder in queries {
+            let err = builder.build().unwrap_err();
+            assert!(matches!(err, Error::CursorMismatch { .. }), "{err}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cached_cursor_should_be_checked_on_each_use() -> Result<()> {
+        let now = Arc::new(AtomicU64::new(1_000_000));
+        let clock = {
+            let now = now.clone();
+            move || UNIX_EPOCH + Duration::from_secs(now.load(Ordering::Relaxed))
+        };
+        let codec = CursorCodec::signed("k1", SECRET)?
+            .with_ttl(Duration::from_secs(60))
+            .with_clock(clock);
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .codec(codec)
+            .build()?;
+        let query = query.with_cursor(&Cursor::Offset(10));
+        query.validate()?;
+        assert_eq!(query.to_sql(), "SELECT * FROM users LIMIT 11 OFFSET 10");
+
+        // unsigned cursors can't expire
+        let unsigned = SqlQueryBuilder::default()
+            .source("users")
+            .codec(CursorCodec::new().with_ttl(Duration::from_secs(60)))
+            .build();
+        assert!(matches!(unsigned, Err(Error::UnsignedTtl)));
+
+        // the cached cursor was issued for the query without the filter
+        let mut changed = query.clone();
+        changed.validate()?;
+        changed.filter = Some(Filter::raw("active"));
+        assert!(matches!(
+            changed.validate(),
+            Err(Error::CursorMismatch { .. })
+        ));
+        let cached = query.clone();
+        cached.validate()?;
+        let changed = SqlQuery {
+            filter: Some(Filter::raw("active")),
+            ..cached
+        };
+        assert!(matches!(
+            changed.validate(),
+            Err(Error::CursorMismatch { .. })
+        ));
+
+        // the cached cursor expires
+        now.fetch_add(120, Ordering::Relaxed);
+        assert!(matches!(
+            query.get_cursor(),
+            Err(Error::CursorExpired { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn offset_query_should_page_within_snapshot() -> Result<()> {
+        let query = SqlQueryBuilder::default().source("users").build()?;
+        let query = query.with_cursor(&Cursor::Snapshot {
+            id: "tx-42".into(),
+            offset: 10,
+        });
+        assert_eq!(query.to_sql(), "SELECT * FROM users LIMIT 11 OFFSET 10");
+
+        let mut data = generate_test_ids(11, 21);
+        let pager = query.get_pager(&mut data)?;
+        assert_eq!(
+            pager.next,
+            Some(Cursor::Snapshot {
+                id: "tx-42".into(),
+                offset: 20
+            })
+        );
+        assert_eq!(
+            pager.prev,
+            Some(Cursor::Snapshot {
+                id: "tx-42".into(),
+                offset: 0
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sql_query_should_generate_count_query() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .projection(vec!["id".into(), "name".into()])
+            .filter(Filter::eq("status", "active"))
+            .order(vec![OrderBy::desc("id")])
+            .cursor(Cursor::Offset(20).encode())
+            .build()?;
+        assert_eq!(
+            query.to_count_sql(),
+            "SELECT COUNT(*) FROM users WHERE status = 'active'"
+        );
+        let stmt = query.to_count_statement(&PostgresDialect);
+        assert_eq!(
+            stmt.sql,
+            r#"SELECT COUNT(*) FROM "users" WHERE "status" = $1"#
+        );
+        assert_eq!(stmt.params, vec!["active".into()]);
+
+        let mut data = generate_test_ids(21, 31);
+        let pager = query.get_pager(&mut data)?.with_total(42);
+        assert_eq!(pager.page, Some(3));
+        assert_eq!(pager.total_pages(), Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn sql_query_should_count_by_policy() -> Result<()> {
+        let builder = SqlQueryBuilder::default()
+            .source("public.users")
+            .total(TotalPolicy::Capped { cap: 1000 })
+            .clone();
+        let query = builder.clone().filter(Filter::eq("status", "a")).build()?;
+        assert_eq!(
+            query.to_count_sql(),
+            "SELECT COUNT(*) FROM (SELECT 1 AS one FROM public.users WHERE status = 'a' LIMIT 1001) t"
+        );
+        let stmt = query.to_count_statement(&MsSqlDialect);
+        assert_eq!(
+            stmt.sql,
+            "SELECT COUNT(*) FROM (SELECT TOP (@p1) 1 AS one FROM [public].[users] WHERE [status] = @p2) t"
+        );
+        assert_eq!(stmt.params, vec![Value::Int(1001), "a".into()]);
+        let dialect = GenericDialect::default();
+        assert_eq!(query.to_total(&dialect, 1001).to_string(), "1000+");
+        assert_eq!(query.to_total(&dialect, 1000), Total::exact(1000));
+
+        // estimated from statistics without a filter, capped otherwise
+        let query = builder
+            .clone()
+            .total(TotalPolicy::Estimated { cap: 1000 })
+            .build()?;
+        let stmt = query.to_count_statement(&PostgresDialect);
+        assert_eq!(
+            stmt.sql,
+            "SELECT GREATEST(reltuples, 0)::bigint FROM pg_class WHERE oid = to_regclass($1)"
+        );
+        assert_eq!(stmt.params, vec![r#""public"."users""#.into()]);
+        assert_eq!(query.to_total(&PostgresDialect, 5000).to_string(), "~5000");
+        let stmt = query.to_count_statement(&MySqlDialect);
+        assert_eq!(stmt.params, vec!["public".into(), "users".into()]);
+        assert_eq!(
+            query.to_count_statement(&SqliteDialect).sql,
+            r#"SELECT COUNT(*) FROM (SELECT 1 AS one FROM "public"."users" LIMIT ?) t"#
+        );
+        assert_eq!(query.to_total(&SqliteDialect, 5000).to_string(), "1000+");
+        Ok(())
+    }
+
+    #[test]
+    fn offset_query_should_jump_to_page() -> Result<()> {
+        let query = SqlQueryBuilder::default().source("users").build()?;
+        let query = query.jump_to_page(5).context("can't jump")?;
+        assert_eq!(query.to_sql(), "SELECT * FROM users LIMIT 11 OFFSET 40");
+
+        let mut data = generate_test_ids(41, 51);
+        let pager = query.get_pager(&mut data)?.with_total(47);
+        assert_eq!(pager.page, Some(5));
+        assert_eq!(pager.page_window(3), vec![3, 4, 5]);
+
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![OrderBy::asc("id")])
+            .mode(PageMode::Keyset)
+            .build()?;
+        assert!(query.jump_to_page(2).is_none());
+
+        // offsets overflowing or out of range for SQL
+        let query = SqlQueryBuilder::default().source("users").build()?;
+        assert!(query.jump_to_page(u64::MAX).is_none());
+        assert!(query.jump_to_page(u64::MAX / 10).is_none());
+        let err = SqlQueryBuilder::default()
+            .source("users")
+            .cursor(Cursor::Offset(i64::MAX as u64 + 1).encode())
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::OffsetTooDeep { .. }), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn query_should_go_to_first_and_last_page() -> Result<()> {
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .page_size(10u64)
+            .build()?
+            .with_cursor(&Cursor::Snapshot {
+                id: "tx-1".into(),
+                offset: 20,
+            });
+        let mut data = generate_test_ids(21, 31);
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(
+            pager.first,
+            Some(Cursor::Snapshot {
+                id: "tx-1".into(),
+                offset: 0
+            })
+        );
+        assert_eq!(pager.last, None);
+        assert!(query.last_page(&pager).is_none());
+        assert_eq!(
+            query.first_page().to_sql(),
+            "SELECT * FROM users LIMIT 11 OFFSET 0"
+        );
+
+        // the last page of 47 items starts at 40, estimates are not enough
+        let last = query.last_page(&pager.clone().with_total(47));
+        assert_eq!(
+            last.context("no last page")?.get_cursor()?,
+            Some(Cursor::Snapshot {
+                id: "tx-1".into(),
+                offset: 40
+            })
+        );
+        assert!(query
+            .last_page(&pager.clone().with_total(Total::estimate(47)))
+            .is_none());
+        assert_eq!(
+            query.pager_with_total(pager, 47).last,
+            Some(Cursor::Snapshot {
+                id: "tx-1".into(),
+                offset: 40
+            })
+        );
+
+        // keyset mode reads the last page backwards
+        let query = SqlQueryBuilder::default()
+            .source("users")
+            .order(vec![OrderBy::asc("id")])
+            .mode(PageMode::Keyset)
+            .filter(Filter::eq("active", true))
+            .build()?;
+        let mut data = generate_test_ids(1, 11);
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(pager.first, None);
+        assert_eq!(pager.last, Some(Cursor::Before(vec![])));
+
+        let query = query.last_page(&pager).context("no last page")?;
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM users WHERE active = TRUE ORDER BY id DESC LIMIT 11"
+        );
+        let mut data: Vec<_> = generate_test_ids(40, 50).into_iter().rev().collect();
+        let pager = query.get_keyset_pager(&mut data)?;
+        assert_eq!(data.first().map(|v| v.sort_key("id")), Some(41.into()));
+        assert_eq!(pager.prev, Some(Cursor::Before(vec![41.into()])));
+        assert_eq!((pager.next, pager.last), (None, None));
+        assert_eq!(pager.first, Some(Cursor::After(vec![])));
+
+        let query = query.first_page();
+        assert_eq!(
+            query.to_sql(),
+            "SELECT * FROM users WHERE active = TRUE ORDER BY id LIMIT 11"
+        );
+        let pager = query.get_keyset_pager(&mut generate_test_ids(1, 11))?;
+        assert_eq!((pager.prev, pager.first), (None, None));
+        Ok(())
+    }
+
+    #[test]
+    fn page_policy_should_clamp_or_reject() -> Result<()> {
+        let builder = SqlQueryBuilder::default().source("users").clone();
+        let size = |builder: &SqlQueryBuilder, size: u64| {
+            builder.clone().page_size(size).build().map(|q| q.page_size)
+        };
+
+        // default policy: 10 by default, up to 100 inclusive
+        assert_eq!(size(&builder, 0)?, 10);
+        assert_eq!(size(&builder, 100)?, 100);
+        assert_eq!(size(&builder, 150)?, 100);
+
+        let export = builder
+            .clone()
+            .page_policy(PagePolicy::new(100, 1, 1000))
+            .clone();
+        assert_eq!(size(&export, 0)?, 100);
+        assert_eq!(size(&export, 1000)?, 1000);
+        assert_eq!(size(&export, 5000)?, 1000);
+
+        let mobile = builder
+            .clone()
+            .page_policy(PagePolicy::new(20, 5, 50).reject_out_of_range())
+            .clone();
+        assert_eq!(size(&mobile, 0)?, 20);
+        assert_eq!(size(&mobile, 5)?, 5);
+        assert_eq!(
+            size(&mobile, 51).unwrap_err().to_string(),
+            "Page size must be between 5-50. Got: 51"
+        );
+        assert!(size(&mobile, 4).is_err());
+
+        let invalid = builder
+            .clone()
+            .page_policy(PagePolicy::new(10, 20, 50))
+            .clone();
+        assert!(matches!(size(&invalid, 0), Err(Error::InvalidPagePolicy)));
+        Ok(())
+    }
+
+    #[test]
+    fn offset_query_should_reject_deep_offset() -> Result<()> {
+        let policy = PagePolicy::default().with_max_offset(1000);
+        let builder = SqlQueryBuilder::default()
+            .source("users")
+            .page_policy(policy)
+            .clone();
+        let query = builder
+            .clone()
+            .cursor(Cursor::Offset(1000).encode())
+            .build()?;
+        let mut data = generate_test_ids(1001, 1011);
+        let pager = query.get_pager(&mut data)?;
+        assert_eq!(pager.next, None);
+        assert_eq!(pager.prev, Some(Cursor::Offset(990)));
+        assert!(query.jump_to_page(101).is_some());
+        assert!(query.jump_to_page(102).is_none());
+
+        let err = builder
+            .clone()
+            .cursor(Cursor::Offset(10_000_000).encode())
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Offset 10000000 exceeds the maximum of 1000"
+        );
+        let err = builder
+            .clone()
+            .page_policy(policy.suggest_keyset())
+            .cursor(Cursor::Offset(1001).encode())
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Offset 1001 exceeds the maximum of 1000. Use keyset pagination to go deeper"
+        );
         Ok(())
     }
 }
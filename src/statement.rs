@@ -0,0 +1,76 @@
+use crate::{Dialect, Value};
+use serde::{Deserialize, Serialize};
+
+/// Placeholder style for bind parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Placeholder {
+    /// `$1`, `$2`, ... (PostgreSQL)
+    Dollar,
+    /// `?` (MySQL, SQLite)
+    Question,
+    /// `@p1`, `@p2`, ... (SQL Server)
+    At,
+    /// `:p1`, `:p2`, ... (Oracle)
+    Colon,
+}
+
+/// SQL text with placeholders and the values to bind to them, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+impl Placeholder {
+    /// render the placeholder for the 1-based parameter `index`
+    pub fn render(&self, index: usize) -> String {
+        match self {
+            Placeholder::Dollar => format!("${index}"),
+            Placeholder::Question => "?".to_owned(),
+            Placeholder::At => format!("@p{index}"),
+            Placeholder::Colon => format!(":p{index}"),
+        }
+    }
+}
+
+/// Collect values either as bind parameters or inline literals while rendering SQL
+pub(crate) struct Binder<'d> {
+    pub dialect: &'d dyn Dialect,
+    inline: bool,
+    params: Vec<Value>,
+}
+
+impl<'d> Binder<'d> {
+    pub fn inline(dialect: &'d dyn Dialect) -> Self {
+        Self {
+            dialect,
+            inline: true,
+            params: Vec::new(),
+        }
+    }
+
+    pub fn params(dialect: &'d dyn Dialect) -> Self {
+        Self {
+            dialect,
+            inline: false,
+            params: Vec::new(),
+        }
+    }
+
+    /// bind the value and return the SQL text referencing it
+    pub fn bind(&mut self, value: &Value) -> String {
+        if self.inline {
+            return self.dialect.literal(value);
+        }
+        self.params.push(value.clone());
+        self.dialect.placeholder().render(self.params.len())
+    }
+
+    pub fn finish(self, sql: String) -> Statement {
+        Statement {
+            sql,
+            params: self.params,
+        }
+    }
+}
@@ -1,5 +1,9 @@
-use crate::error::*;
+use crate::{error::*, Value};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305,
+};
 use snafu::ResultExt;
 
 #[inline(always)]
@@ -14,18 +18,137 @@ pub(crate) fn b64_decode(s: &str, output: &mut [u8]) -> Result<usize> {
         .context(Base64DecodeSnafu { s })
 }
 
-/// encode u64 to base64 string
+/// encode u64 to base64 string, the format of version 0 cursors
+#[cfg(test)]
 #[inline(always)]
 pub(crate) fn encode_u64(input: u64) -> String {
     let s = input.to_string();
     b64_encode(s)
 }
 
-/// decode base64 string to u64
-#[inline(always)]
-pub(crate) fn decode_u64(s: &str) -> Result<u64> {
-    let mut bytes = [0u8; 32];
-    let len = b64_decode(s, &mut bytes)?;
-    let s = std::str::from_utf8(&bytes[..len]).context(InvalidUtf8Snafu)?;
-    s.parse::<u64>().context(InvalidNumberSnafu { s })
+/// decode base64 string to a byte vector
+pub(crate) fn b64_decode_vec(s: &str) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; base64::decoded_len_estimate(s.len())];
+    let len = b64_decode(s, &mut buf)?;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+/// encrypt with XChaCha20-Poly1305 under a random nonce. The nonce is prepended to the output
+pub(crate) fn encrypt(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    let ciphertext = cipher
+        .encrypt(&nonce, payload)
+        .expect("buffer is large enough for the ciphertext");
+    [nonce.as_slice(), &ciphertext].concat()
+}
+
+/// decrypt data produced by `encrypt`. Return None if it was tampered with or the key is wrong
+pub(crate) fn decrypt(key: &[u8; 32], aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let (nonce, msg) = data.split_first_chunk::<24>()?;
+    cipher.decrypt(nonce.into(), Payload { msg, aad }).ok()
+}
+
+const TAG_NULL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_STRING: u8 = 4;
+
+/// encode values into a compact binary form: a tag byte followed by the payload of each value
+pub(crate) fn encode_values(values: &[Value], buf: &mut Vec<u8>) {
+    encode_varint(values.len() as u64, buf);
+    for value in values {
+        match value {
+            Value::Null => buf.push(TAG_NULL),
+            Value::Bool(v) => buf.extend([TAG_BOOL, *v as u8]),
+            Value::Int(v) => {
+                buf.push(TAG_INT);
+                buf.extend(v.to_be_bytes());
+            }
+            Value::Float(v) => {
+                buf.push(TAG_FLOAT);
+                buf.extend(v.to_bits().to_be_bytes());
+            }
+            Value::String(v) => {
+                buf.push(TAG_STRING);
+                encode_str(v, buf);
+            }
+        }
+    }
+}
+
+/// decode values produced by `encode_values`. Return None if the input is malformed
+pub(crate) fn decode_values(buf: &mut &[u8]) -> Option<Vec<Value>> {
+    let len = decode_varint(buf)?;
+    let mut values = Vec::new();
+    for _ in 0..len {
+        let (tag, rest) = buf.split_first()?;
+        *buf = rest;
+        let value = match *tag {
+            TAG_NULL => Value::Null,
+            TAG_BOOL => Value::Bool(take(buf, 1)?[0] != 0),
+            TAG_INT => Value::Int(i64::from_be_bytes(take(buf, 8)?.try_into().ok()?)),
+            TAG_FLOAT => {
+                let v = f64::from_bits(u64::from_be_bytes(take(buf, 8)?.try_into().ok()?));
+                // SQL can't compare with infinite or NaN floats
+                Value::Float(Some(v).filter(|v| v.is_finite())?)
+            }
+            TAG_STRING => Value::String(decode_str(buf)?),
+            _ => return None,
+        };
+        values.push(value);
+    }
+    Some(values)
+}
+
+/// encode a string as its length followed by the UTF-8 bytes
+pub(crate) fn encode_str(s: &str, buf: &mut Vec<u8>) {
+    encode_varint(s.len() as u64, buf);
+    buf.extend(s.as_bytes());
+}
+
+/// decode a string produced by `encode_str`
+pub(crate) fn decode_str(buf: &mut &[u8]) -> Option<String> {
+    let len = decode_varint(buf)?;
+    let bytes = take(buf, usize::try_from(len).ok()?)?;
+    Some(std::str::from_utf8(bytes).ok()?.to_owned())
+}
+
+/// encode u64 as LEB128 varint
+pub(crate) fn encode_varint(mut v: u64, buf: &mut Vec<u8>) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+/// decode LEB128 varint
+pub(crate) fn decode_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = buf.split_first()?;
+        *buf = rest;
+        v |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(v);
+        }
+    }
+    None
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buf.len() < n {
+        return None;
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Some(head)
 }
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// A scalar SQL value, e.g. one column of a keyset cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// render the value as an inline SQL literal. SQL has no literal for infinite and NaN floats,
+    /// they are rendered as NULL so they match no row
+    pub fn to_sql_literal(&self) -> Cow<'_, str> {
+        match self {
+            Value::Null => "NULL".into(),
+            Value::Bool(true) => "TRUE".into(),
+            Value::Bool(false) => "FALSE".into(),
+            Value::Int(v) => v.to_string().into(),
+            Value::Float(v) if !v.is_finite() => "NULL".into(),
+            Value::Float(v) => v.to_string().into(),
+            Value::String(v) => format!("'{}'", v.replace('\'', "''")).into(),
+        }
+    }
+}
+
+// floats are compared by their bits so that `Value` can be `Eq`
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::String(a), Value::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Value::Int(v as i64)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Int(v)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(v: u32) -> Self {
+        Value::Int(v as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Float(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(v.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map(Into::into).unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_should_render_sql_literal() {
+        assert_eq!(Value::Null.to_sql_literal(), "NULL");
+        assert_eq!(Value::from(true).to_sql_literal(), "TRUE");
+        assert_eq!(Value::from(42).to_sql_literal(), "42");
+        assert_eq!(Value::from(1.5).to_sql_literal(), "1.5");
+        assert_eq!(Value::from(f64::INFINITY).to_sql_literal(), "NULL");
+        assert_eq!(Value::from(f64::NAN).to_sql_literal(), "NULL");
+        assert_eq!(Value::from("it's").to_sql_literal(), "'it''s'");
+        assert_eq!(Value::from(None::<i64>).to_sql_literal(), "NULL");
+    }
+}
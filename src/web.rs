@@ -0,0 +1,248 @@
+use crate::{error::*, Pager, Resource, SortKey, SqlQuery, Total};
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Extract a validated `SqlQuery` from the query string of the request, parsed by the `Resource`
+/// of the state (see `Resource::parse_query`). Bad parameters are rejected with a 400 response,
+/// a misconfigured resource with a 500 response, whose details are only logged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest(pub SqlQuery<'static>);
+
+/// A page of items with its pager, rendered as JSON with the fields of the pager next to the
+/// items. Cursors are encoded for the client
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub pager: Pager<String>,
+}
+
+#[derive(Serialize)]
+struct PageBody<'a, T> {
+    items: &'a [T],
+    #[serde(flatten)]
+    pager: &'a Pager<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: String,
+}
+
+impl<S> FromRequestParts<S> for PageRequest
+where
+    S: Send + Sync,
+    Resource<'static>: FromRef<S>,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let resource = Resource::from_ref(state);
+        resource
+            .parse_query(parts.uri.query().unwrap_or_default())
+            .map(PageRequest)
+    }
+}
+
+impl<T> Page<T> {
+    /// get the pager for the fetched items (page_size + 1 of them, see `SqlQuery::get_pager`),
+    /// offset mode only
+    pub fn new(query: &SqlQuery, mut items: Vec<T>) -> Result<Self> {
+        let pager = query.get_pager(&mut items)?;
+        Ok(Self {
+            items,
+            pager: pager.map(|cursor| query.encode_cursor(&cursor)),
+        })
+    }
+
+    /// like `new`, but supports keyset mode too (see `SqlQuery::get_keyset_pager`)
+    pub fn keyset(query: &SqlQuery, mut items: Vec<T>) -> Result<Self>
+    where
+        T: SortKey,
+    {
+        let pager = query.get_keyset_pager(&mut items)?;
+        Ok(Self {
+            items,
+            pager: pager.map(|cursor| query.encode_cursor(&cursor)),
+        })
+    }
+
+    /// attach the total, e.g. from `SqlQuery::to_total`. In offset mode an exact total also gives
+    /// the last page (see `SqlQuery::pager_with_total`)
+    pub fn with_total(self, query: &SqlQuery, total: impl Into<Total>) -> Self {
+        let mut pager = self.pager.with_total(total);
+        if pager.last.is_none() {
+            pager.last = query
+                .last_cursor(pager.total)
+                .map(|cursor| query.encode_cursor(&cursor));
+        }
+        Self { pager, ..self }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        Json(PageBody {
+            items: &self.items,
+            pager: &self.pager,
+        })
+        .into_response()
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        // errors in the configuration of the server, not in the request
+        let status = match self {
+            Error::InvalidPagePolicy
+            | Error::InvalidSource
+            | Error::InvalidIdentifier { .. }
+            | Error::InvalidOrder { .. }
+            | Error::InvalidKeyset
+            | Error::MissingSortKey
+            | Error::UnsignedTtl
+            | Error::WeakSecret { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        // configuration details are only for the server logs
+        let message = if status.is_server_error() {
+            tracing::error!(error = %self, "invalid pagination configuration");
+            "Internal server error".to_owned()
+        } else {
+            self.client_message()
+        };
+        let body = ErrorBody {
+            code: self.code(),
+            message,
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrderBy, PagePolicy, PostgresDialect, ResourceBuilder};
+    use anyhow::Result;
+    use axum::{body::to_bytes, http::Request};
+    use serde_json::{json, Value};
+
+    #[derive(Serialize)]
+    struct User {
+        id: i64,
+    }
+
+    impl SortKey for User {
+        fn sort_key(&self, _column: &str) -> crate::Value {
+            self.id.into()
+        }
+    }
+
+    fn users() -> Result<Resource<'static>> {
+        Ok(ResourceBuilder::default()
+            .source("users")
+            .sortable(vec!["id".into()])
+            .order(vec![OrderBy::asc("id")])
+            .build()?)
+    }
+
+    async fn extract(resource: &Resource<'static>, uri: &str) -> Result<SqlQuery<'static>, Error> {
+        let (mut parts, _) = Request::get(uri).body(()).unwrap().into_parts();
+        PageRequest::from_request_parts(&mut parts, resource)
+            .await
+            .map(|PageRequest(query)| query)
+    }
+
+    /// the limit and offset bound to the statement of the query
+    fn statement(query: &SqlQuery) -> (i64, i64) {
+        let stmt = query.to_statement(&PostgresDialect);
+        assert_eq!(
+            stmt.sql,
+            r#"SELECT * FROM "users" ORDER BY "id" DESC LIMIT $1 OFFSET $2"#
+        );
+        match stmt.params[..] {
+            [crate::Value::Int(limit), crate::Value::Int(offset)] => (limit, offset),
+            _ => panic!("unexpected params: {:?}", stmt.params),
+        }
+    }
+
+    async fn json(response: Response) -> Result<(StatusCode, Value)> {
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await?;
+        Ok((status, serde_json::from_slice(&body)?))
+    }
+
+    #[tokio::test]
+    async fn page_request_should_extract_query() -> Result<()> {
+        let resource = users()?;
+        let query = extract(&resource, "/users?page_size=2&sort=-id").await?;
+        assert_eq!(statement(&query), (3, 0));
+
+        let page =
+            Page::new(&query, (1..=3).map(|id| User { id }).collect())?.with_total(&query, 5);
+        let (status, body) = json(page.into_response()).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["items"], json!([{"id": 1}, {"id": 2}]));
+        assert_eq!(body["total"], json!({"count": 5, "kind": "exact"}));
+        assert_eq!(body["page"], json!(1));
+        assert_eq!(body["prev"], Value::Null);
+
+        // the next cursor can be sent back as is
+        let next = body["next"].as_str().unwrap_or_default();
+        let query = extract(
+            &resource,
+            &format!("/users?page_size=2&sort=-id&cursor={next}"),
+        )
+        .await?;
+        assert_eq!(statement(&query), (3, 2));
+
+        // the last page is known from the exact total
+        let last = body["last"].as_str().unwrap_or_default();
+        let query = extract(
+            &resource,
+            &format!("/users?page_size=2&sort=-id&cursor={last}"),
+        )
+        .await?;
+        assert_eq!(statement(&query), (3, 4));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn page_request_should_reject_bad_params() -> Result<()> {
+        let resource = users()?;
+        let err = extract(&resource, "/users?sort=name").await.unwrap_err();
+        let (status, body) = json(err.into_response()).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            json!({"code": "UNSORTABLE_FIELD", "message": "Field cannot be sorted: name"})
+        );
+
+        let err = extract(&resource, "/users?cursor=abc").await.unwrap_err();
+        let (status, body) = json(err.into_response()).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            json!({"code": "INVALID_CURSOR", "message": "Invalid cursor"})
+        );
+
+        // a misconfigured resource is not the fault of the client
+        let resource = Resource {
+            page_policy: PagePolicy::new(10, 20, 5),
+            ..resource
+        };
+        let err = extract(&resource, "/users").await.unwrap_err();
+        let (status, body) = json(err.into_response()).await?;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body,
+            json!({"code": "INVALID_PAGE_POLICY", "message": "Internal server error"})
+        );
+        Ok(())
+    }
+}